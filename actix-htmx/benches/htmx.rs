@@ -0,0 +1,122 @@
+use actix_htmx::__bench::{simple_header, trigger_json};
+use actix_htmx::{Htmx, HtmxMiddleware, TriggerType};
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::test::{self, TestRequest};
+use actix_web::{rt, web, App, FromRequest, HttpMessage, HttpRequest, HttpResponse};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use futures_util::FutureExt;
+use indexmap::IndexMap;
+
+fn full_htmx_request() -> TestRequest {
+    TestRequest::get()
+        .uri("/")
+        .insert_header(("hx-request", "true"))
+        .insert_header(("hx-boosted", "true"))
+        .insert_header(("hx-current-url", "https://example.com/items?page=2"))
+        .insert_header(("hx-history-restore-request", "false"))
+        .insert_header(("hx-prompt", "yes"))
+        .insert_header(("hx-target", "item-list"))
+        .insert_header(("hx-trigger", "load-more"))
+        .insert_header(("hx-trigger-name", "load-more"))
+}
+
+fn extract(req: &HttpRequest) -> Htmx {
+    Htmx::extract(req).now_or_never().unwrap().unwrap()
+}
+
+fn from_request(c: &mut Criterion) {
+    let mut group = c.benchmark_group("from_request");
+
+    let uncached = full_htmx_request().to_http_request();
+    group.bench_function("parse_headers", |b| b.iter(|| extract(&uncached)));
+
+    let cached = full_htmx_request().to_http_request();
+    let htmx = extract(&cached);
+    cached.extensions_mut().insert(htmx);
+    group.bench_function("cached_extension", |b| b.iter(|| extract(&cached)));
+
+    group.finish();
+}
+
+async fn queue_triggers(
+    htmx: Htmx,
+    count: web::Path<usize>,
+    with_message: web::Data<bool>,
+) -> HttpResponse {
+    (0..*count).for_each(|i| {
+        let message = with_message.then(|| format!("{{\"id\": {}}}", i));
+        htmx.trigger_event(format!("event-{}", i), message, Some(TriggerType::Standard));
+    });
+    HttpResponse::Ok().finish()
+}
+
+fn trigger_serialization(c: &mut Criterion) {
+    let system = rt::System::new();
+    let mut group = c.benchmark_group("trigger_serialization");
+
+    for with_message in [false, true] {
+        let app = system.block_on(test::init_service(
+            App::new()
+                .app_data(web::Data::new(with_message))
+                .wrap(HtmxMiddleware)
+                .route("/{count}", web::get().to(queue_triggers)),
+        ));
+        let kind = if with_message { "json" } else { "simple" };
+
+        for count in [1, 10, 50] {
+            group.bench_with_input(BenchmarkId::new(kind, count), &count, |b, count| {
+                b.iter(|| {
+                    let req = full_htmx_request().uri(&format!("/{}", count)).to_request();
+                    let res: ServiceResponse = system.block_on(app.call(req)).unwrap();
+                    res
+                })
+            });
+        }
+    }
+
+    group.finish();
+}
+
+fn trigger_maps(count: usize, with_message: bool) -> IndexMap<String, Option<String>> {
+    (0..count)
+        .map(|i| {
+            let message = with_message.then(|| format!("{{\"id\": {}}}", i));
+            (format!("event-{}", i), message)
+        })
+        .collect()
+}
+
+fn header_serializers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("header_serializers");
+
+    for count in [1, 10, 50] {
+        let simple = trigger_maps(count, false);
+        group.bench_with_input(
+            BenchmarkId::new("simple_header", count),
+            &simple,
+            |b, map| b.iter(|| simple_header(map)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("trigger_json", count),
+            &simple,
+            |b, map| b.iter(|| trigger_json(map)),
+        );
+
+        let with_messages = trigger_maps(count, true);
+        group.bench_with_input(
+            BenchmarkId::new("trigger_json_messages", count),
+            &with_messages,
+            |b, map| b.iter(|| trigger_json(map)),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    from_request,
+    trigger_serialization,
+    header_serializers
+);
+criterion_main!(benches);
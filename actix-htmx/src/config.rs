@@ -0,0 +1,42 @@
+use log::Level;
+
+use crate::SwapType;
+
+/// App wide defaults read by the middleware, registered with
+/// `App::app_data(Data::new(HtmxConfig { .. }))`. Anything a handler sets on [`crate::Htmx`]
+/// takes precedence over these. Rejecting non-htmx requests is not an app wide default: use
+/// [`crate::HtmxOnlyMiddleware`] on htmx-only scopes, or
+/// [`crate::HtmxMiddlewareConfig::reject_non_htmx`] when every route is htmx-only.
+#[derive(Clone, Debug, Default)]
+pub struct HtmxConfig {
+    /// Sent as `HX-Reswap` on htmx requests whose handler didn't set one.
+    pub default_reswap: Option<SwapType>,
+    pub auto_vary_header: bool,
+    /// Overrides [`crate::HtmxMiddlewareConfig::log_level`] when set.
+    pub log_level: Option<Level>,
+}
+
+/// Per scope or per resource overrides of [`HtmxConfig`], registered with
+/// `.app_data(web::Data::new(HtmxRouteConfig::new().default_reswap(..)))`. Unset values fall
+/// back to the app wide config; handler set headers still take precedence over both.
+#[derive(Clone, Debug, Default)]
+pub struct HtmxRouteConfig {
+    pub default_reswap: Option<SwapType>,
+    pub auto_vary_header: Option<bool>,
+}
+
+impl HtmxRouteConfig {
+    pub fn new() -> HtmxRouteConfig {
+        HtmxRouteConfig::default()
+    }
+
+    pub fn default_reswap(mut self, swap_type: SwapType) -> Self {
+        self.default_reswap = Some(swap_type);
+        self
+    }
+
+    pub fn auto_vary_header(mut self, auto_vary_header: bool) -> Self {
+        self.auto_vary_header = Some(auto_vary_header);
+        self
+    }
+}
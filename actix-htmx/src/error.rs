@@ -0,0 +1,35 @@
+use actix_web::http::StatusCode;
+use actix_web::ResponseError;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HtmxError {
+    MissingPrompt,
+    MissingTarget,
+    MissingTrigger,
+    MissingTriggerName,
+    MissingCurrentUrl,
+    InvalidHeaderValue(String),
+}
+
+impl fmt::Display for HtmxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HtmxError::MissingPrompt => write!(f, "missing HX-Prompt header"),
+            HtmxError::MissingTarget => write!(f, "missing HX-Target header"),
+            HtmxError::MissingTrigger => write!(f, "missing HX-Trigger header"),
+            HtmxError::MissingTriggerName => write!(f, "missing HX-Trigger-Name header"),
+            HtmxError::MissingCurrentUrl => write!(f, "missing HX-Current-URL header"),
+            HtmxError::InvalidHeaderValue(header) => write!(f, "invalid value for header: {}", header),
+        }
+    }
+}
+
+impl Error for HtmxError {}
+
+impl ResponseError for HtmxError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
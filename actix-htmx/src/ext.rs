@@ -0,0 +1,127 @@
+use actix_web::HttpRequest;
+
+use crate::headers::RequestHeaders;
+use crate::htmx::{AsBool, AsOptionString};
+
+/// Reads htmx request headers straight off an [`HttpRequest`], always under their standard
+/// `hx-` names, even when [`crate::HtmxMiddleware::with_prefix`] is in use.
+pub trait HtmxRequestExt {
+    fn is_htmx(&self) -> bool;
+    fn is_boosted(&self) -> bool;
+    fn is_history_restore_request(&self) -> bool;
+    fn hx_current_url(&self) -> Option<String>;
+    fn hx_prompt(&self) -> Option<String>;
+    fn hx_target(&self) -> Option<String>;
+    fn hx_trigger(&self) -> Option<String>;
+    fn hx_trigger_name(&self) -> Option<String>;
+}
+
+impl HtmxRequestExt for HttpRequest {
+    fn is_htmx(&self) -> bool {
+        self.headers().get(RequestHeaders::HX_REQUEST).as_bool()
+    }
+
+    fn is_boosted(&self) -> bool {
+        self.headers().get(RequestHeaders::HX_BOOSTED).as_bool()
+    }
+
+    fn is_history_restore_request(&self) -> bool {
+        self.headers()
+            .get(RequestHeaders::HX_HISTORY_RESTORE_REQUEST)
+            .as_bool()
+    }
+
+    fn hx_current_url(&self) -> Option<String> {
+        self.headers()
+            .get(RequestHeaders::HX_CURRENT_URL)
+            .as_option_string()
+    }
+
+    fn hx_prompt(&self) -> Option<String> {
+        self.headers().get(RequestHeaders::HX_PROMPT).as_option_string()
+    }
+
+    fn hx_target(&self) -> Option<String> {
+        self.headers().get(RequestHeaders::HX_TARGET).as_option_string()
+    }
+
+    fn hx_trigger(&self) -> Option<String> {
+        self.headers().get(RequestHeaders::HX_TRIGGER).as_option_string()
+    }
+
+    fn hx_trigger_name(&self) -> Option<String> {
+        self.headers()
+            .get(RequestHeaders::HX_TRIGGER_NAME)
+            .as_option_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::body::MessageBody;
+    use actix_web::dev::{ServiceRequest, ServiceResponse};
+    use actix_web::error::ErrorNotFound;
+    use actix_web::middleware::{from_fn, Next};
+    use actix_web::test::{self, TestRequest};
+    use actix_web::{web, App, Error, HttpResponse};
+
+    /// A hand-written guard: only htmx requests reach the partials.
+    async fn htmx_only(
+        req: ServiceRequest,
+        next: Next<impl MessageBody>,
+    ) -> Result<ServiceResponse<impl MessageBody>, Error> {
+        if !req.request().is_htmx() {
+            return Err(ErrorNotFound("not an htmx request"));
+        }
+        next.call(req).await
+    }
+
+    #[actix_web::test]
+    async fn custom_guard_admits_only_htmx_requests() {
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(htmx_only))
+                .route("/", web::get().to(|| async { HttpResponse::Ok().finish() })),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/").insert_header(("hx-request", "true"));
+        assert!(test::call_service(&app, req.to_request()).await.status().is_success());
+
+        let err = test::try_call_service(&app, TestRequest::get().uri("/").to_request())
+            .await
+            .map(|res| res.status())
+            .unwrap_err();
+        assert_eq!(err.as_response_error().status_code(), 404);
+
+        let req = TestRequest::get().uri("/").insert_header(("hx2-request", "true"));
+        assert!(test::try_call_service(&app, req.to_request()).await.is_err());
+    }
+
+    #[test]
+    fn reads_standard_header_names_only() {
+        let req = TestRequest::default()
+            .insert_header(("hx-request", "true"))
+            .insert_header(("hx-boosted", "true"))
+            .insert_header(("hx-current-url", "https://example.com/"))
+            .insert_header(("hx-prompt", "yes"))
+            .insert_header(("hx-target", "list"))
+            .insert_header(("hx-trigger", "save-button"))
+            .insert_header(("hx-trigger-name", "save"))
+            .to_http_request();
+        assert!(req.is_htmx() && req.is_boosted() && !req.is_history_restore_request());
+        assert_eq!(req.hx_current_url().as_deref(), Some("https://example.com/"));
+        assert_eq!(req.hx_prompt().as_deref(), Some("yes"));
+        assert_eq!(req.hx_target().as_deref(), Some("list"));
+        assert_eq!(req.hx_trigger().as_deref(), Some("save-button"));
+        assert_eq!(req.hx_trigger_name().as_deref(), Some("save"));
+
+        let prefixed = TestRequest::default()
+            .insert_header(("hx2-request", "true"))
+            .insert_header(("hx2-target", "list"))
+            .to_http_request();
+        assert!(!prefixed.is_htmx());
+        assert_eq!(prefixed.hx_target(), None);
+    }
+}
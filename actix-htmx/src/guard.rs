@@ -0,0 +1,92 @@
+use actix_web::guard::{self, Guard, GuardContext};
+
+use crate::headers::{prefixed, RequestHeaders};
+use crate::htmx::AsBool;
+
+/// Admits only htmx requests. Reads the standard `hx-request` header; use
+/// [`HtmxGuard::with_prefix`] alongside [`crate::HtmxMiddleware::with_prefix`].
+pub struct HtmxGuard;
+
+/// Admits only non-htmx requests. Reads the standard `hx-request` header; use
+/// [`NonHtmxGuard::with_prefix`] alongside [`crate::HtmxMiddleware::with_prefix`].
+pub struct NonHtmxGuard;
+
+fn is_htmx(ctx: &GuardContext<'_>, prefix: Option<&str>) -> bool {
+    ctx.head()
+        .headers()
+        .get(prefixed(RequestHeaders::HX_REQUEST, prefix).as_ref())
+        .as_bool()
+}
+
+impl HtmxGuard {
+    /// Checks `{prefix}-request` instead of `hx-request`.
+    pub fn with_prefix(prefix: &'static str) -> impl Guard {
+        guard::fn_guard(move |ctx| is_htmx(ctx, Some(prefix)))
+    }
+}
+
+impl NonHtmxGuard {
+    /// Checks `{prefix}-request` instead of `hx-request`.
+    pub fn with_prefix(prefix: &'static str) -> impl Guard {
+        guard::fn_guard(move |ctx| !is_htmx(ctx, Some(prefix)))
+    }
+}
+
+impl Guard for HtmxGuard {
+    fn check(&self, ctx: &GuardContext<'_>) -> bool {
+        is_htmx(ctx, None)
+    }
+}
+
+impl Guard for NonHtmxGuard {
+    fn check(&self, ctx: &GuardContext<'_>) -> bool {
+        !is_htmx(ctx, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{self, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+
+    #[test]
+    fn guards_check_hx_request() {
+        let htmx = TestRequest::default().insert_header(("hx-request", "true")).to_srv_request();
+        let plain = TestRequest::default().to_srv_request();
+
+        assert!(HtmxGuard.check(&htmx.guard_ctx()));
+        assert!(!HtmxGuard.check(&plain.guard_ctx()));
+        assert!(!NonHtmxGuard.check(&htmx.guard_ctx()));
+        assert!(NonHtmxGuard.check(&plain.guard_ctx()));
+    }
+
+    #[test]
+    fn prefixed_guards_check_prefixed_header() {
+        let prefixed = TestRequest::default().insert_header(("hx2-request", "true")).to_srv_request();
+        let standard = TestRequest::default().insert_header(("hx-request", "true")).to_srv_request();
+
+        assert!(HtmxGuard::with_prefix("hx2").check(&prefixed.guard_ctx()));
+        assert!(!HtmxGuard::with_prefix("hx2").check(&standard.guard_ctx()));
+        assert!(!NonHtmxGuard::with_prefix("hx2").check(&prefixed.guard_ctx()));
+        assert!(NonHtmxGuard::with_prefix("hx2").check(&standard.guard_ctx()));
+    }
+
+    #[actix_web::test]
+    async fn guards_route_htmx_and_full_page_requests() {
+        let app = test::init_service(
+            App::new().service(
+                web::resource("/")
+                    .route(web::get().guard(HtmxGuard).to(|| async { HttpResponse::Ok().body("partial") }))
+                    .route(web::get().guard(NonHtmxGuard).to(|| async { HttpResponse::Ok().body("full") })),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/").insert_header(("hx-request", "true")).to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, "partial");
+
+        let req = TestRequest::get().uri("/").to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, "full");
+    }
+}
@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+
 pub(crate) struct RequestHeaders;
 pub(crate) struct ResponseHeaders;
 
@@ -10,6 +12,7 @@ impl RequestHeaders {
     pub(crate) const HX_TARGET: &'static str = "hx-target";
     pub(crate) const HX_TRIGGER: &'static str = "hx-trigger";
     pub(crate) const HX_TRIGGER_NAME: &'static str = "hx-trigger-name";
+    pub(crate) const HX_VERSION: &'static str = "hx-version";
 }
 
 impl ResponseHeaders {
@@ -24,4 +27,15 @@ impl ResponseHeaders {
     pub(crate) const HX_RETARGET: &'static str = "hx-retarget";
     pub(crate) const HX_RESELECT: &'static str = "hx-reselect";
     pub(crate) const HX_REPLACE_URL: &'static str = "hx-replace-url";
+    pub(crate) const HX_STOP_POLLING: &'static str = "hx-stop-polling";
+    pub(crate) const HX_POLLING_INTERVAL: &'static str = "hx-polling-interval";
+}
+
+/// Swaps the leading `hx` of a header name for `prefix`, for htmx builds that send
+/// `hx2-request` and friends.
+pub(crate) fn prefixed<'a>(name: &'a str, prefix: Option<&str>) -> Cow<'a, str> {
+    match (prefix, name.strip_prefix("hx-")) {
+        (Some(prefix), Some(rest)) => Cow::Owned(format!("{}-{}", prefix, rest)),
+        _ => Cow::Borrowed(name),
+    }
 }
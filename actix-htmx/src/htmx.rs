@@ -1,15 +1,22 @@
 use actix_web::dev::{Payload, ServiceRequest};
-use actix_web::error::Error;
-use actix_web::http::header::HeaderValue;
-use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use actix_web::error::{Error, ErrorBadRequest};
+use actix_web::http::header::{self, HeaderMap, HeaderValue};
+use actix_web::{FromRequest, HttpMessage, HttpRequest, HttpResponse, Responder};
 use futures_util::future::{ready, Ready};
 use indexmap::IndexMap;
-use std::cell::RefCell;
+use log::{log, Level};
+use std::cell::{Ref, RefCell, RefMut};
 use std::collections::HashMap;
+use std::error::Error as StdError;
 use std::fmt;
+use std::future::Future;
+use std::mem;
 use std::rc::Rc;
+use std::str::FromStr;
+use std::time::Duration;
 
-use crate::headers::{RequestHeaders, ResponseHeaders};
+use crate::headers::{prefixed, RequestHeaders, ResponseHeaders};
+use crate::{CssSelector, HtmxChain, HtmxError, HxReswap, HxUrl};
 
 macro_rules! collection {
     ($($k:expr => $v:expr),* $(,)?) => {{
@@ -18,6 +25,26 @@ macro_rules! collection {
     }};
 }
 
+macro_rules! option_accessors {
+    ($($getter:ident => $or:ident, $or_default:ident, $or_else:ident;)*) => {
+        $(
+            pub fn $or(&self, default: impl Into<String>) -> String {
+                self.$getter().unwrap_or_else(|| default.into())
+            }
+
+            pub fn $or_default(&self) -> String {
+                self.$getter().unwrap_or_default()
+            }
+
+            pub fn $or_else(&self, f: impl FnOnce() -> String) -> String {
+                self.$getter().unwrap_or_else(f)
+            }
+        )*
+    };
+}
+
+pub(crate) use option_accessors;
+
 #[derive(Clone)]
 pub struct Htmx {
     inner: Rc<RefCell<HtmxInner>>,
@@ -26,14 +53,57 @@ pub struct Htmx {
     pub history_restore_request: bool,
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum TriggerType {
     Standard,
     AfterSettle,
     AfterSwap,
 }
 
+/// A name usable with [`Htmx::trigger_typed_event`], letting an app keep its events in an enum
+/// instead of loose strings.
+///
+/// ```
+/// use actix_htmx::{Htmx, HtmxEventName, TriggerType};
+///
+/// enum AppEvent {
+///     ItemCreated,
+///     ItemDeleted,
+/// }
+///
+/// impl HtmxEventName for AppEvent {
+///     fn event_name(&self) -> &str {
+///         match self {
+///             AppEvent::ItemCreated => "item-created",
+///             AppEvent::ItemDeleted => "item-deleted",
+///         }
+///     }
+/// }
+///
+/// let htmx = Htmx::default();
+/// htmx.trigger_typed_event(&AppEvent::ItemCreated, None, None);
+/// assert!(htmx.has_trigger("item-created", TriggerType::Standard));
+/// ```
+pub trait HtmxEventName {
+    fn event_name(&self) -> &str;
+}
+
+impl HtmxEventName for &str {
+    fn event_name(&self) -> &str {
+        self
+    }
+}
+
+impl HtmxEventName for String {
+    fn event_name(&self) -> &str {
+        self.as_str()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub enum SwapType {
+    /// The default, matching htmx's own default swap style.
+    #[default]
     InnerHtml,
     OuterHtml,
     BeforeBegin,
@@ -44,59 +114,398 @@ pub enum SwapType {
     None,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownSwapType(pub String);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownTriggerType(pub String);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RedirectError {
+    NotAbsoluteUrl(String),
+    NotRelativePath(String),
+}
+
+#[derive(Debug)]
+pub enum SwapModifier {
+    Swap(Duration),
+    Settle(Duration),
+    Scroll(CssSelector),
+    Show(CssSelector),
+    IgnoreTitle,
+    FocusScroll(bool),
+}
+
+#[derive(Clone, Debug)]
+pub struct HxOobSpec {
+    pub selector: String,
+    pub swap_type: SwapType,
+}
+
+#[derive(Clone, Debug)]
+pub struct HtmxEventSpec {
+    pub name: String,
+    pub message: Option<String>,
+    pub trigger_type: TriggerType,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HtmxRequestInfo {
+    pub is_htmx: bool,
+    pub boosted: bool,
+    pub history_restore_request: bool,
+    pub current_url: Option<String>,
+    pub prompt: Option<String>,
+    pub target: Option<String>,
+    pub trigger: Option<String>,
+    pub trigger_name: Option<String>,
+}
+
+/// The triggers and response headers queued on an [`Htmx`], detached from the request so it
+/// can be sent elsewhere (with the `serde` feature) and applied to a later response.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HtmxResponseState {
+    pub standard_triggers: IndexMap<String, Option<String>>,
+    pub after_settle_triggers: IndexMap<String, Option<String>>,
+    pub after_swap_triggers: IndexMap<String, Option<String>>,
+    pub response_headers: IndexMap<String, String>,
+}
+
+/// Header combinations whose outcome on the client is unclear, found by
+/// [`Htmx::validate_response`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HtmxResponseWarning {
+    /// Both `HX-Redirect` and `HX-Location` are set.
+    ConflictingRedirectHeaders,
+    /// `HX-Refresh` reloads the page, so an `HX-Push-Url` alongside it has no effect.
+    RefreshWithPushUrl,
+    /// `HX-Refresh` reloads the page, so an `HX-Replace-Url` alongside it has no effect.
+    RefreshWithReplaceUrl,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HtmxDiagnostics {
+    pub standard_trigger_count: usize,
+    pub after_settle_trigger_count: usize,
+    pub after_swap_trigger_count: usize,
+    pub response_header_count: usize,
+    pub is_htmx: bool,
+}
+
+impl fmt::Display for HtmxResponseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HtmxResponseWarning::ConflictingRedirectHeaders => {
+                write!(f, "both HX-Redirect and HX-Location are set")
+            }
+            HtmxResponseWarning::RefreshWithPushUrl => {
+                write!(f, "HX-Push-Url is set alongside HX-Refresh")
+            }
+            HtmxResponseWarning::RefreshWithReplaceUrl => {
+                write!(f, "HX-Replace-Url is set alongside HX-Refresh")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
 enum DataType {
     String(Option<String>),
     Bool(bool),
 }
 
+impl AsRef<str> for SwapType {
+    fn as_ref(&self) -> &str {
+        match self {
+            SwapType::InnerHtml => "innerHTML",
+            SwapType::OuterHtml => "outerHTML",
+            SwapType::BeforeBegin => "beforebegin",
+            SwapType::AfterBegin => "afterbegin",
+            SwapType::BeforeEnd => "beforeend",
+            SwapType::AfterEnd => "afterend",
+            SwapType::Delete => "delete",
+            SwapType::None => "none",
+        }
+    }
+}
+
 impl fmt::Display for SwapType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+impl TriggerType {
+    pub(crate) fn header_name(&self) -> &'static str {
+        match self {
+            TriggerType::Standard => ResponseHeaders::HX_TRIGGER,
+            TriggerType::AfterSettle => ResponseHeaders::HX_TRIGGER_AFTER_SETTLE,
+            TriggerType::AfterSwap => ResponseHeaders::HX_TRIGGER_AFTER_SWAP,
+        }
+    }
+}
+
+impl AsRef<str> for TriggerType {
+    fn as_ref(&self) -> &str {
+        self.header_name()
+    }
+}
+
+impl fmt::Display for TriggerType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+impl TryFrom<&str> for TriggerType {
+    type Error = UnknownTriggerType;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            ResponseHeaders::HX_TRIGGER => Ok(TriggerType::Standard),
+            ResponseHeaders::HX_TRIGGER_AFTER_SETTLE => Ok(TriggerType::AfterSettle),
+            ResponseHeaders::HX_TRIGGER_AFTER_SWAP => Ok(TriggerType::AfterSwap),
+            _ => Err(UnknownTriggerType(value.to_string())),
+        }
+    }
+}
+
+impl TryFrom<String> for TriggerType {
+    type Error = UnknownTriggerType;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        TriggerType::try_from(value.as_str())
+    }
+}
+
+impl FromStr for TriggerType {
+    type Err = UnknownTriggerType;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TriggerType::try_from(s)
+    }
+}
+
+impl fmt::Display for UnknownTriggerType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown trigger type: {}", self.0)
+    }
+}
+
+impl StdError for UnknownTriggerType {}
+
+impl TryFrom<&str> for SwapType {
+    type Error = UnknownSwapType;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "innerHTML" => Ok(SwapType::InnerHtml),
+            "outerHTML" => Ok(SwapType::OuterHtml),
+            "beforebegin" => Ok(SwapType::BeforeBegin),
+            "afterbegin" => Ok(SwapType::AfterBegin),
+            "beforeend" => Ok(SwapType::BeforeEnd),
+            "afterend" => Ok(SwapType::AfterEnd),
+            "delete" => Ok(SwapType::Delete),
+            "none" => Ok(SwapType::None),
+            _ => Err(UnknownSwapType(value.to_string())),
+        }
+    }
+}
+
+impl FromStr for SwapType {
+    type Err = UnknownSwapType;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SwapType::try_from(s)
+    }
+}
+
+impl fmt::Display for UnknownSwapType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown swap type: {}", self.0)
+    }
+}
+
+impl StdError for UnknownSwapType {}
+
+impl fmt::Display for RedirectError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            SwapType::InnerHtml => write!(f, "innerHTML"),
-            SwapType::OuterHtml => write!(f, "outerHTML"),
-            SwapType::BeforeBegin => write!(f, "beforebegin"),
-            SwapType::AfterBegin => write!(f, "afterbegin"),
-            SwapType::BeforeEnd => write!(f, "beforeend"),
-            SwapType::AfterEnd => write!(f, "afterend"),
-            SwapType::Delete => write!(f, "delete"),
-            SwapType::None => write!(f, "none"),
+            RedirectError::NotAbsoluteUrl(url) => write!(f, "not an absolute url: {}", url),
+            RedirectError::NotRelativePath(path) => write!(f, "not a relative path: {}", path),
         }
     }
 }
 
-struct HtmxInner {
-    standard_triggers: IndexMap<String, Option<String>>,
-    after_settle_triggers: IndexMap<String, Option<String>>,
-    after_swap_triggers: IndexMap<String, Option<String>>,
-    response_headers: IndexMap<String, String>,
+impl StdError for RedirectError {}
+
+impl fmt::Display for SwapModifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SwapModifier::Swap(duration) => write!(f, "swap:{}ms", duration.as_millis()),
+            SwapModifier::Settle(duration) => write!(f, "settle:{}ms", duration.as_millis()),
+            SwapModifier::Scroll(selector) => write!(f, "scroll:{}", selector),
+            SwapModifier::Show(selector) => write!(f, "show:{}", selector),
+            SwapModifier::IgnoreTitle => write!(f, "ignoreTitle:true"),
+            SwapModifier::FocusScroll(focus_scroll) => write!(f, "focus-scroll:{}", focus_scroll),
+        }
+    }
+}
+
+impl HxOobSpec {
+    pub fn new(selector: impl Into<String>, swap_type: SwapType) -> HxOobSpec {
+        HxOobSpec {
+            selector: selector.into(),
+            swap_type,
+        }
+    }
+}
+
+impl fmt::Display for HxOobSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.swap_type, self.selector)
+    }
+}
+
+impl<N: Into<String>> From<(N, Option<String>, TriggerType)> for HtmxEventSpec {
+    fn from((name, message, trigger_type): (N, Option<String>, TriggerType)) -> Self {
+        HtmxEventSpec {
+            name: name.into(),
+            message,
+            trigger_type,
+        }
+    }
+}
+
+impl From<HtmxEventSpec> for (String, Option<String>, TriggerType) {
+    fn from(spec: HtmxEventSpec) -> Self {
+        (spec.name, spec.message, spec.trigger_type)
+    }
+}
+
+impl fmt::Display for HtmxDiagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "is_htmx: {}, triggers: {} standard, {} after settle, {} after swap, response headers: {}",
+            self.is_htmx,
+            self.standard_trigger_count,
+            self.after_settle_trigger_count,
+            self.after_swap_trigger_count,
+            self.response_header_count
+        )
+    }
+}
+
+pub(crate) struct HtmxInner {
+    pub(crate) standard_triggers: IndexMap<String, Option<String>>,
+    pub(crate) after_settle_triggers: IndexMap<String, Option<String>>,
+    pub(crate) after_swap_triggers: IndexMap<String, Option<String>>,
+    pub(crate) response_headers: IndexMap<String, String>,
     request_headers: IndexMap<String, DataType>,
-    simple_trigger: HashMap<TriggerType, bool>,
+    /// Every `HX-*` request header, under its standard name. Other headers, such as cookies,
+    /// are never copied.
+    pub(crate) raw_request_headers: Vec<(String, HeaderValue)>,
+    pub(crate) oob_specs: Vec<HxOobSpec>,
+    pub(crate) vary_hx_request: bool,
+    pub(crate) log_target: &'static str,
+    pub(crate) log_level: Level,
 }
 
 impl HtmxInner {
     pub fn new(req: &HttpRequest) -> HtmxInner {
+        HtmxInner::from_headers(req.headers())
+    }
+
+    pub(crate) fn from_headers(headers: &HeaderMap) -> HtmxInner {
+        HtmxInner::from_headers_with_prefix(headers, None)
+    }
+
+    /// Parses the request headers, looking them up under `prefix` instead of `hx` when set.
+    /// Values are stored under the standard names so the accessors don't need to know.
+    pub(crate) fn from_headers_with_prefix(headers: &HeaderMap, prefix: Option<&str>) -> HtmxInner {
+        let headers_get = |name: &str| headers.get(prefixed(name, prefix).as_ref());
         let request_headers = collection![
-            RequestHeaders::HX_REQUEST.to_string() => DataType::Bool(req.headers().get(RequestHeaders::HX_REQUEST).as_bool()),
-            RequestHeaders::HX_BOOSTED.to_string() => DataType::Bool(req.headers().get(RequestHeaders::HX_BOOSTED).as_bool()),
-            RequestHeaders::HX_CURRENT_URL.to_string() => DataType::String(req.headers().get(RequestHeaders::HX_CURRENT_URL).as_option_string()),
-            RequestHeaders::HX_HISTORY_RESTORE_REQUEST.to_string() => DataType::Bool(req.headers().get(RequestHeaders::HX_HISTORY_RESTORE_REQUEST).as_bool()),
-            RequestHeaders::HX_PROMPT.to_string() => DataType::String(req.headers().get(RequestHeaders::HX_PROMPT).as_option_string()),
-            RequestHeaders::HX_TARGET.to_string() => DataType::String(req.headers().get(RequestHeaders::HX_TARGET).as_option_string()),
-            RequestHeaders::HX_TRIGGER.to_string() => DataType::String(req.headers().get(RequestHeaders::HX_TRIGGER).as_option_string()),
-            RequestHeaders::HX_TRIGGER_NAME.to_string() => DataType::String(req.headers().get(RequestHeaders::HX_TRIGGER_NAME).as_option_string()),
+            RequestHeaders::HX_REQUEST.to_string() => DataType::Bool(headers_get(RequestHeaders::HX_REQUEST).as_bool()),
+            RequestHeaders::HX_BOOSTED.to_string() => DataType::Bool(headers_get(RequestHeaders::HX_BOOSTED).as_bool()),
+            RequestHeaders::HX_CURRENT_URL.to_string() => DataType::String(headers_get(RequestHeaders::HX_CURRENT_URL).as_option_string()),
+            RequestHeaders::HX_HISTORY_RESTORE_REQUEST.to_string() => DataType::Bool(headers_get(RequestHeaders::HX_HISTORY_RESTORE_REQUEST).as_bool()),
+            RequestHeaders::HX_PROMPT.to_string() => DataType::String(headers_get(RequestHeaders::HX_PROMPT).as_option_string()),
+            RequestHeaders::HX_TARGET.to_string() => DataType::String(headers_get(RequestHeaders::HX_TARGET).as_option_string()),
+            RequestHeaders::HX_TRIGGER.to_string() => DataType::String(headers_get(RequestHeaders::HX_TRIGGER).as_option_string()),
+            RequestHeaders::HX_TRIGGER_NAME.to_string() => DataType::String(headers_get(RequestHeaders::HX_TRIGGER_NAME).as_option_string()),
+            RequestHeaders::HX_VERSION.to_string() => DataType::String(headers_get(RequestHeaders::HX_VERSION).as_option_string()),
         ];
 
+        let raw_prefix = prefixed("hx-", prefix);
+        let raw_request_headers = headers
+            .iter()
+            .filter_map(|(name, value)| {
+                let rest = name.as_str().strip_prefix(raw_prefix.as_ref())?;
+                Some((format!("hx-{}", rest), value.clone()))
+            })
+            .collect();
+
         HtmxInner {
             request_headers,
+            raw_request_headers,
             response_headers: IndexMap::new(),
             standard_triggers: IndexMap::new(),
             after_settle_triggers: IndexMap::new(),
             after_swap_triggers: IndexMap::new(),
-            simple_trigger: HashMap::new(),
+            oob_specs: Vec::new(),
+            vary_hx_request: false,
+            log_target: "actix_htmx",
+            log_level: Level::Warn,
+        }
+    }
+
+    /// Where warnings about rejected values go, so they follow the middleware's
+    /// [`crate::HtmxMiddlewareConfig::log_target`] and log level.
+    pub(crate) fn log_to(&mut self, target: &'static str, level: Level) {
+        self.log_target = target;
+        self.log_level = level;
+    }
+
+    pub(crate) fn triggers(&self, trigger_type: &TriggerType) -> &IndexMap<String, Option<String>> {
+        match trigger_type {
+            TriggerType::Standard => &self.standard_triggers,
+            TriggerType::AfterSettle => &self.after_settle_triggers,
+            TriggerType::AfterSwap => &self.after_swap_triggers,
         }
     }
 
-    fn get_bool_header(&self, header_name: &str) -> bool {
+    pub(crate) fn triggers_mut(&mut self, trigger_type: &TriggerType) -> &mut IndexMap<String, Option<String>> {
+        match trigger_type {
+            TriggerType::Standard => &mut self.standard_triggers,
+            TriggerType::AfterSettle => &mut self.after_settle_triggers,
+            TriggerType::AfterSwap => &mut self.after_swap_triggers,
+        }
+    }
+
+    pub(crate) fn is_simple_trigger(&self, trigger_type: &TriggerType) -> bool {
+        self.triggers(trigger_type).values().all(Option::is_none)
+    }
+
+    pub(crate) fn validate(&self) -> Vec<HtmxResponseWarning> {
+        let has = |header: &str| self.response_headers.contains_key(header);
+        let mut warnings = Vec::new();
+        if has(ResponseHeaders::HX_REDIRECT) && has(ResponseHeaders::HX_LOCATION) {
+            warnings.push(HtmxResponseWarning::ConflictingRedirectHeaders);
+        }
+        if has(ResponseHeaders::HX_REFRESH) {
+            if has(ResponseHeaders::HX_PUSH_URL) {
+                warnings.push(HtmxResponseWarning::RefreshWithPushUrl);
+            }
+            if has(ResponseHeaders::HX_REPLACE_URL) {
+                warnings.push(HtmxResponseWarning::RefreshWithReplaceUrl);
+            }
+        }
+        warnings
+    }
+
+    pub(crate) fn get_bool_header(&self, header_name: &str) -> bool {
         self.request_headers
             .get(header_name)
             .map(|data_type| match data_type {
@@ -106,17 +515,11 @@ impl HtmxInner {
             .unwrap_or(false)
     }
 
-    fn get_string_header(&self, header_name: &str) -> Option<String> {
+    pub(crate) fn get_string_header(&self, header_name: &str) -> Option<String> {
         self.request_headers
             .get(header_name)
             .map(|data_type| match data_type {
-                DataType::String(s) => {
-                    if let Some(s) = s {
-                        Some(s.clone())
-                    } else {
-                        None
-                    }
-                },
+                DataType::String(s) => s.clone(),
                 _ => None,
             })
             .unwrap_or(None)
@@ -124,13 +527,13 @@ impl HtmxInner {
 }
 
 impl Htmx {
-    fn from_inner(inner: Rc<RefCell<HtmxInner>>) -> Htmx {
-        let is_htmx = inner.borrow().get_bool_header(RequestHeaders::HX_REQUEST);
-        let boosted = inner.borrow().get_bool_header(RequestHeaders::HX_BOOSTED);
-        let history_restore_request = inner.borrow().get_bool_header(RequestHeaders::HX_HISTORY_RESTORE_REQUEST);
+    fn from_inner(inner: HtmxInner) -> Htmx {
+        let is_htmx = inner.get_bool_header(RequestHeaders::HX_REQUEST);
+        let boosted = inner.get_bool_header(RequestHeaders::HX_BOOSTED);
+        let history_restore_request = inner.get_bool_header(RequestHeaders::HX_HISTORY_RESTORE_REQUEST);
 
         Htmx {
-            inner,
+            inner: Rc::new(RefCell::new(inner)),
             is_htmx,
             boosted,
             history_restore_request,
@@ -138,135 +541,63 @@ impl Htmx {
     }
 
     pub fn new(req: &ServiceRequest) -> Htmx {
-        let req = req.request();
-        let inner = Rc::new(RefCell::new(HtmxInner::new(req)));
-        Htmx::from_inner(inner)
+        Htmx::from_inner(HtmxInner::new(req.request()))
     }
 
-    pub fn current_url(&self) -> Option<String> {
-        self.inner.borrow().get_string_header(RequestHeaders::HX_CURRENT_URL)
+    pub(crate) fn with_header_prefix(req: &ServiceRequest, prefix: Option<&str>) -> Htmx {
+        Htmx::from_inner(HtmxInner::from_headers_with_prefix(req.headers(), prefix))
     }
 
-    pub fn prompt(&self) -> Option<String> {
-        self.inner.borrow().get_string_header(RequestHeaders::HX_PROMPT)
+    pub(crate) fn log_to(&self, target: &'static str, level: Level) {
+        self.write_inner().log_to(target, level);
     }
 
-    pub fn target(&self) -> Option<String> {
-        self.inner.borrow().get_string_header(RequestHeaders::HX_TARGET)
-    }
-
-    pub fn trigger(&self) -> Option<String> {
-        self.inner.borrow().get_string_header(RequestHeaders::HX_TRIGGER)
-    }
-
-    pub fn trigger_name(&self) -> Option<String> {
-        self.inner.borrow().get_string_header(RequestHeaders::HX_TRIGGER_NAME)
-    }
-
-    pub fn trigger_event(&self, name: String, message: Option<String>, trigger_type: Option<TriggerType>) {
-        let trigger_type = trigger_type.unwrap_or(TriggerType::Standard);
-        match trigger_type {
-            TriggerType::Standard => {
-                if message != None {
-                    _ = self.inner.borrow_mut().simple_trigger.entry(TriggerType::Standard).or_insert(false);
-                }
-                self.inner.borrow_mut().standard_triggers.insert(name, message);
-            }
-            TriggerType::AfterSettle => {
-                if message != None {
-                    _ = self.inner.borrow_mut().simple_trigger.entry(TriggerType::AfterSettle).or_insert(false);
-                }
-                self.inner
-                    .borrow_mut()
-                    .after_settle_triggers
-                    .insert(name, message);
-            }
-            TriggerType::AfterSwap => {
-                if message != None {
-                    _ = self.inner.borrow_mut().simple_trigger.entry(TriggerType::AfterSwap).or_insert(false);
-                }
-                self.inner
-                    .borrow_mut()
-                    .after_swap_triggers
-                    .insert(name, message);
-            }
-        }
+    pub(crate) fn read_inner(&self) -> Ref<'_, HtmxInner> {
+        self.inner.borrow()
     }
 
-    pub fn redirect(&self, path: String) {
-        self.inner
-            .borrow_mut()
-            .response_headers
-            .insert(ResponseHeaders::HX_REDIRECT.to_string(), path);
+    fn write_inner(&self) -> RefMut<'_, HtmxInner> {
+        self.inner.borrow_mut()
     }
 
-    pub fn redirect_with_swap(&self, path: String) {
-        self.inner
-            .borrow_mut()
-            .response_headers
-            .insert(ResponseHeaders::HX_LOCATION.to_string(), path);
-    }
-
-    pub fn refresh(&self) {
-        self.inner
-            .borrow_mut()
-            .response_headers
-            .insert(ResponseHeaders::HX_REFRESH.to_string(), "true".to_string());
-    }
-
-    pub fn push_url(&self, path: String) {
-        self.inner
-            .borrow_mut()
-            .response_headers
-            .insert(ResponseHeaders::HX_PUSH_URL.to_string(), path);
-    }
-
-    pub fn replace_url(&self, path: String) {
-        self.inner
-            .borrow_mut()
-            .response_headers
-            .insert(ResponseHeaders::HX_REPLACE_URL.to_string(), path);
-    }
-
-    pub fn reswap(&self, swap_type: SwapType) {
-        self.inner.borrow_mut().response_headers.insert(
-            ResponseHeaders::HX_RESWAP.to_string(),
-            swap_type.to_string(),
-        );
-    }
-
-    pub fn retarget(&self, selector: String) {
-        self.inner.borrow_mut().response_headers.insert(
-            ResponseHeaders::HX_RETARGET.to_string(),
-            selector.to_string(),
-        );
-    }
-
-    pub fn reselect(&self, selector: String) {
-        self.inner.borrow_mut().response_headers.insert(
-            ResponseHeaders::HX_RESELECT.to_string(),
-            selector.to_string(),
-        );
-    }
+    crate::methods::htmx_methods!();
+}
 
-    pub(crate) fn get_triggers(&self, trigger_type: TriggerType) -> IndexMap<String, Option<String>> {
-        match trigger_type {
-            TriggerType::Standard => self.inner.borrow().standard_triggers.clone(),
-            TriggerType::AfterSettle => self.inner.borrow().after_settle_triggers.clone(),
-            TriggerType::AfterSwap => self.inner.borrow().after_swap_triggers.clone(),
-        }
+/// An empty instance for a non-htmx request, with no request headers and nothing queued.
+impl Default for Htmx {
+    fn default() -> Self {
+        Htmx::from_inner(HtmxInner::from_headers(&HeaderMap::new()))
     }
+}
 
-    pub(crate) fn is_simple_trigger(&self, trigger_type: TriggerType) -> bool {
-        match trigger_type {
-            TriggerType::Standard => *self.inner.borrow().simple_trigger.get(&TriggerType::Standard).unwrap_or(&true),
-            TriggerType::AfterSettle => *self.inner.borrow().simple_trigger.get(&TriggerType::AfterSettle).unwrap_or(&true),
-            TriggerType::AfterSwap => *self.inner.borrow().simple_trigger.get(&TriggerType::AfterSwap).unwrap_or(&true),
-        }
+impl fmt::Debug for Htmx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.inner.borrow();
+        f.debug_struct("Htmx")
+            .field("is_htmx", &self.is_htmx)
+            .field("boosted", &self.boosted)
+            .field("history_restore_request", &self.history_restore_request)
+            .field("response_headers", &inner.response_headers)
+            .field("standard_trigger_count", &inner.standard_triggers.len())
+            .field("after_settle_trigger_count", &inner.after_settle_triggers.len())
+            .field("after_swap_trigger_count", &inner.after_swap_triggers.len())
+            .finish()
     }
+}
 
-    pub(crate) fn get_response_headers(&self) -> IndexMap<String, String> {
-        self.inner.borrow().response_headers.clone()
+// Written by hand so the cloned request headers, which can hold cookies and credentials,
+// never end up in logs.
+impl fmt::Debug for HtmxInner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HtmxInner")
+            .field("standard_triggers", &self.standard_triggers)
+            .field("after_settle_triggers", &self.after_settle_triggers)
+            .field("after_swap_triggers", &self.after_swap_triggers)
+            .field("response_headers", &self.response_headers)
+            .field("request_headers", &self.request_headers)
+            .field("oob_specs", &self.oob_specs)
+            .field("vary_hx_request", &self.vary_hx_request)
+            .finish_non_exhaustive()
     }
 }
 
@@ -280,17 +611,15 @@ impl FromRequest for Htmx {
             return ready(Ok(htmx.clone()));
         }
 
-        let inner = Rc::new(RefCell::new(HtmxInner::new(req)));
-
-        ready(Ok(Htmx::from_inner(inner)))
+        ready(Ok(Htmx::from_inner(HtmxInner::new(req))))
     }
 }
 
-trait AsBool {
+pub(crate) trait AsBool {
     fn as_bool(&self) -> bool;
 }
 
-trait AsOptionString {
+pub(crate) trait AsOptionString {
     fn as_option_string(&self) -> Option<String>;
 }
 
@@ -322,4 +651,1185 @@ impl AsOptionString for Option<&HeaderValue> {
             None => None,
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{self, TestRequest};
+    use actix_web::{web, App};
+
+    /// Runs `setup` in a handler behind [`crate::HtmxMiddleware`] and returns the response
+    /// headers it wrote.
+    async fn response_headers(setup: fn(&Htmx)) -> HeaderMap {
+        let app = test::init_service(App::new().wrap(crate::HtmxMiddleware).route(
+            "/",
+            web::get().to(move |htmx: Htmx| async move {
+                setup(&htmx);
+                HttpResponse::Ok().finish()
+            }),
+        ))
+        .await;
+        let req = TestRequest::get().uri("/").insert_header(("hx-request", "true"));
+        test::call_service(&app, req.to_request()).await.headers().clone()
+    }
+
+    fn with_current_url(url: &str) -> Htmx {
+        Htmx::mock_from_headers(&[("hx-request", "true"), ("hx-current-url", url)])
+    }
+
+    #[test]
+    fn current_url_is_raw_header_value() {
+        let htmx = with_current_url("https://example.com/todos?page=2");
+        assert_eq!(htmx.current_url().as_deref(), Some("https://example.com/todos?page=2"));
+        assert_eq!(Htmx::mock().current_url(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "url-parsing")]
+    fn current_url_path_and_query() {
+        let htmx = with_current_url("https://example.com/todos?page=2#top");
+        assert_eq!(htmx.current_url_path().as_deref(), Some("/todos"));
+        assert_eq!(htmx.current_url_query().as_deref(), Some("page=2"));
+
+        let htmx = with_current_url("/todos?page=2");
+        assert_eq!(htmx.current_url_path().as_deref(), Some("/todos?page=2"));
+        assert_eq!(htmx.current_url_query().as_deref(), Some("page=2"));
+    }
+
+    #[test]
+    #[cfg(feature = "url-parsing")]
+    fn current_url_matches_on_segment_boundaries() {
+        let htmx = with_current_url("https://example.com/admin/users?page=2");
+        assert!(htmx.current_url_matches("users"));
+        assert!(htmx.current_url_matches("/users"));
+        assert!(htmx.current_url_matches("admin/users"));
+        assert!(htmx.current_url_matches("/admin/users"));
+        assert!(!htmx.current_url_matches("ers"));
+        assert!(!htmx.current_url_matches(""));
+
+        let htmx = with_current_url("https://example.com/superusers");
+        assert!(!htmx.current_url_matches("users"));
+        assert!(!htmx.current_url_matches("/users"));
+    }
+
+    #[test]
+    #[cfg(feature = "url-parsing")]
+    fn current_url_starts_with_on_segment_boundaries() {
+        let htmx = with_current_url("https://example.com/admin/users");
+        assert!(htmx.current_url_starts_with("/admin"));
+        assert!(htmx.current_url_starts_with("/admin/"));
+        assert!(htmx.current_url_starts_with("/"));
+        assert!(!htmx.current_url_starts_with("/adm"));
+        assert!(!htmx.current_url_starts_with(""));
+
+        let htmx = with_current_url("https://example.com/administrator");
+        assert!(!htmx.current_url_starts_with("/admin"));
+    }
+
+    #[test]
+    #[cfg(feature = "url-parsing")]
+    fn current_url_is_root() {
+        assert!(with_current_url("https://example.com/").current_url_is_root());
+        assert!(with_current_url("https://example.com").current_url_is_root());
+        assert!(!with_current_url("https://example.com/todos").current_url_is_root());
+        assert!(!Htmx::mock().current_url_is_root());
+    }
+
+    #[test]
+    fn reswap_with_modifiers_keeps_modifier_order() {
+        let htmx = Htmx::mock();
+        htmx.reswap_with_modifiers(
+            SwapType::InnerHtml,
+            &[
+                SwapModifier::Swap(Duration::from_millis(300)),
+                SwapModifier::Scroll(CssSelector::new("#top")),
+                SwapModifier::Settle(Duration::from_millis(100)),
+                SwapModifier::IgnoreTitle,
+                SwapModifier::Show(CssSelector::new("#list")),
+                SwapModifier::FocusScroll(false),
+            ],
+        );
+        assert_eq!(
+            htmx.get_response_header("hx-reswap").as_deref(),
+            Some("innerHTML swap:300ms scroll:#top settle:100ms ignoreTitle:true show:#list focus-scroll:false")
+        );
+    }
+
+    #[test]
+    fn reswap_with_no_modifiers_is_bare_swap_type() {
+        let htmx = Htmx::mock();
+        htmx.reswap_with_modifiers(SwapType::Delete, &[]);
+        assert_eq!(htmx.get_response_header("hx-reswap").as_deref(), Some("delete"));
+    }
+
+    #[test]
+    fn swap_modifier_durations_are_whole_milliseconds() {
+        assert_eq!(SwapModifier::Swap(Duration::from_secs(1)).to_string(), "swap:1000ms");
+        assert_eq!(SwapModifier::Settle(Duration::from_micros(1500)).to_string(), "settle:1ms");
+        assert_eq!(SwapModifier::Swap(Duration::ZERO).to_string(), "swap:0ms");
+    }
+
+    #[actix_web::test]
+    async fn removed_triggers_are_not_written() {
+        let headers = response_headers(|htmx| {
+            htmx.trigger_event("kept".to_string(), None, None);
+            htmx.trigger_event("dropped".to_string(), None, None);
+            htmx.trigger_event("settled".to_string(), None, Some(TriggerType::AfterSettle));
+            htmx.trigger_event("swapped".to_string(), None, Some(TriggerType::AfterSwap));
+            assert!(htmx.remove_trigger("dropped", TriggerType::Standard));
+            htmx.clear_triggers(TriggerType::AfterSettle);
+        })
+        .await;
+        assert_eq!(headers.get("hx-trigger").unwrap(), "kept");
+        assert!(headers.get("hx-trigger-after-settle").is_none());
+        assert_eq!(headers.get("hx-trigger-after-swap").unwrap(), "swapped");
+    }
+
+    #[test]
+    fn remove_missing_trigger_returns_false() {
+        let htmx = Htmx::mock();
+        assert!(!htmx.remove_trigger("missing", TriggerType::Standard));
+        htmx.trigger_event("event".to_string(), None, Some(TriggerType::AfterSwap));
+        assert!(!htmx.remove_trigger("event", TriggerType::Standard));
+        assert!(htmx.has_trigger("event", TriggerType::AfterSwap));
+    }
+
+    #[test]
+    fn clear_all_triggers_empties_every_type() {
+        let htmx = Htmx::mock();
+        htmx.trigger_event("a".to_string(), None, None);
+        htmx.trigger_event("b".to_string(), None, Some(TriggerType::AfterSettle));
+        htmx.trigger_event("c".to_string(), None, Some(TriggerType::AfterSwap));
+        htmx.clear_all_triggers();
+        assert_eq!(htmx.total_trigger_count(), 0);
+    }
+
+    #[test]
+    fn response_header_introspection() {
+        let htmx = Htmx::mock();
+        assert!(!htmx.has_redirect());
+        assert!(!htmx.has_response_header("HX-Redirect"));
+
+        htmx.redirect("/new");
+        assert!(htmx.has_response_header("HX-Redirect"));
+        assert!(htmx.has_response_header("hx-redirect"));
+        assert_eq!(htmx.get_response_header("HX-Redirect").as_deref(), Some("/new"));
+        assert!(htmx.has_redirect());
+        assert!(!htmx.has_refresh());
+        assert!(!htmx.has_reswap());
+
+        htmx.refresh();
+        htmx.reswap(SwapType::OuterHtml);
+        assert!(htmx.has_refresh());
+        assert!(htmx.has_reswap());
+        assert_eq!(htmx.get_response_header("hx-reswap").as_deref(), Some("outerHTML"));
+        assert_eq!(htmx.get_response_header("hx-retarget"), None);
+    }
+
+    #[actix_web::test]
+    async fn oob_specs_are_recorded_in_order_and_not_written() {
+        let htmx = Htmx::mock();
+        htmx.oob_swap(HxOobSpec::new("#count", SwapType::InnerHtml));
+        htmx.oob_swap(HxOobSpec::new("#flash", SwapType::OuterHtml));
+
+        let specs = htmx.get_oob_specs();
+        assert_eq!(specs.len(), 2);
+        assert_eq!(specs[0].selector, "#count");
+        assert_eq!(specs[0].to_string(), "innerHTML:#count");
+        assert_eq!(specs[1].to_string(), "outerHTML:#flash");
+
+        let headers = response_headers(|htmx| htmx.oob_swap(HxOobSpec::new("#count", SwapType::InnerHtml))).await;
+        assert!(headers.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn trigger_event_batch_writes_each_type() {
+        let headers = response_headers(|htmx| {
+            htmx.trigger_event_batch([
+                ("created", None, TriggerType::Standard),
+                ("settled", Some("{\"id\": 1}".to_string()), TriggerType::AfterSettle),
+                ("swapped", None, TriggerType::AfterSwap),
+            ]);
+            htmx.trigger_event_batch(vec![HtmxEventSpec {
+                name: "updated".to_string(),
+                message: None,
+                trigger_type: TriggerType::Standard,
+            }]);
+        })
+        .await;
+        assert_eq!(headers.get("hx-trigger").unwrap(), "created,updated");
+        assert_eq!(headers.get("hx-trigger-after-settle").unwrap(), r#"{"settled": {"id": 1}}"#);
+        assert_eq!(headers.get("hx-trigger-after-swap").unwrap(), "swapped");
+    }
+
+    #[actix_web::test]
+    async fn polling_headers_are_written() {
+        let headers = response_headers(|htmx| htmx.stop_polling()).await;
+        assert_eq!(headers.get("hx-stop-polling").unwrap(), "true");
+
+        let headers = response_headers(|htmx| htmx.set_polling_interval(2000)).await;
+        assert_eq!(headers.get("hx-polling-interval").unwrap(), "2000ms");
+        assert!(headers.get("hx-stop-polling").is_none());
+    }
+
+    #[test]
+    fn url_setters_accept_hx_url_and_strings() {
+        let htmx = Htmx::mock();
+        htmx.redirect(HxUrl::try_new("/checked").unwrap());
+        htmx.push_url("/pushed");
+        htmx.replace_url(String::from("/replaced"));
+        assert_eq!(htmx.get_response_header("hx-redirect").as_deref(), Some("/checked"));
+        assert_eq!(htmx.get_response_header("hx-push-url").as_deref(), Some("/pushed"));
+        assert_eq!(htmx.get_response_header("hx-replace-url").as_deref(), Some("/replaced"));
+
+        assert!(HxUrl::try_new("not a url").is_err());
+    }
+
+    #[test]
+    fn swap_type_round_trips_through_strings() {
+        let all = [
+            SwapType::InnerHtml,
+            SwapType::OuterHtml,
+            SwapType::BeforeBegin,
+            SwapType::AfterBegin,
+            SwapType::BeforeEnd,
+            SwapType::AfterEnd,
+            SwapType::Delete,
+            SwapType::None,
+        ];
+        for swap_type in all {
+            assert_eq!(SwapType::try_from(swap_type.as_ref()), Ok(swap_type.clone()));
+            assert_eq!(swap_type.to_string().parse::<SwapType>(), Ok(swap_type));
+        }
+        assert_eq!(SwapType::try_from("innerHTML").unwrap(), SwapType::InnerHtml);
+        assert_eq!(SwapType::try_from("sideways"), Err(UnknownSwapType("sideways".to_string())));
+    }
+
+    #[test]
+    fn redirect_external_and_redirect_path_validate() {
+        let htmx = Htmx::mock();
+        assert_eq!(htmx.redirect_external("https://other.com/a"), Ok(()));
+        assert_eq!(htmx.get_response_header("hx-redirect").as_deref(), Some("https://other.com/a"));
+        assert!(htmx.redirect_external("//cdn.example.com").is_ok());
+        assert_eq!(
+            htmx.redirect_external("/local"),
+            Err(RedirectError::NotAbsoluteUrl("/local".to_string()))
+        );
+
+        let htmx = Htmx::mock();
+        assert_eq!(htmx.redirect_path("/todos"), Ok(()));
+        assert_eq!(htmx.get_response_header("hx-redirect").as_deref(), Some("/todos"));
+        assert_eq!(
+            htmx.redirect_path("//evil.com"),
+            Err(RedirectError::NotRelativePath("//evil.com".to_string()))
+        );
+        assert!(htmx.redirect_path("https://other.com").is_err());
+        assert_eq!(htmx.get_response_header("hx-redirect").as_deref(), Some("/todos"));
+    }
+
+    #[test]
+    fn require_helpers_reject_with_bad_request() {
+        let status = |result: actix_web::Result<()>| result.unwrap_err().as_response_error().status_code();
+
+        let htmx = Htmx::mock_from_headers(&[
+            ("hx-request", "true"),
+            ("hx-boosted", "true"),
+            ("hx-prompt", "yes"),
+            ("hx-target", "list"),
+        ]);
+        assert!(htmx.require_htmx().is_ok());
+        assert!(htmx.require_boosted().is_ok());
+        assert!(htmx.require_prompt().is_ok());
+        assert!(htmx.require_target().is_ok());
+
+        let htmx = Htmx::mock_with(false, false);
+        assert_eq!(status(htmx.require_htmx()), StatusCode::BAD_REQUEST);
+        assert_eq!(status(htmx.require_boosted()), StatusCode::BAD_REQUEST);
+        assert_eq!(status(htmx.require_prompt()), StatusCode::BAD_REQUEST);
+        assert_eq!(status(htmx.require_target()), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn conditional_redirect_branches_on_is_htmx() {
+        let htmx = Htmx::mock();
+        let res = htmx.conditional_redirect("/partial", "/full");
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(htmx.get_response_header("hx-redirect").as_deref(), Some("/partial"));
+
+        let htmx = Htmx::mock_with(false, false);
+        let res = htmx.conditional_redirect("/partial", "/full");
+        assert_eq!(res.status(), StatusCode::SEE_OTHER);
+        assert_eq!(res.headers().get(header::LOCATION).unwrap(), "/full");
+        assert!(!htmx.has_redirect());
+    }
+
+    #[test]
+    fn conditional_replace_url_only_for_htmx() {
+        let htmx = Htmx::mock();
+        htmx.conditional_replace_url("/page/2");
+        assert_eq!(htmx.get_response_header("hx-replace-url").as_deref(), Some("/page/2"));
+
+        let htmx = Htmx::mock_with(false, false);
+        htmx.conditional_replace_url("/page/2");
+        assert!(!htmx.has_response_header("hx-replace-url"));
+    }
+
+    #[test]
+    fn trigger_type_displays_its_header_name() {
+        assert_eq!(TriggerType::Standard.to_string(), ResponseHeaders::HX_TRIGGER);
+        assert_eq!(TriggerType::AfterSettle.to_string(), ResponseHeaders::HX_TRIGGER_AFTER_SETTLE);
+        assert_eq!(TriggerType::AfterSwap.to_string(), ResponseHeaders::HX_TRIGGER_AFTER_SWAP);
+        assert_eq!(TriggerType::AfterSwap.as_ref(), "hx-trigger-after-swap");
+        assert_eq!(SwapType::OuterHtml.to_string(), "outerHTML");
+        assert_eq!(SwapType::OuterHtml.as_ref(), "outerHTML");
+    }
+
+    #[actix_web::test]
+    async fn disabled_url_headers_are_false() {
+        let headers = response_headers(|htmx| {
+            htmx.disable_push_url();
+            htmx.disable_replace_url();
+        })
+        .await;
+        assert_eq!(headers.get("hx-push-url").unwrap(), "false");
+        assert_eq!(headers.get("hx-replace-url").unwrap(), "false");
+
+        let headers = response_headers(|_| {}).await;
+        assert!(headers.get("hx-push-url").is_none());
+    }
+
+    #[test]
+    fn combined_retarget_helpers_match_individual_calls() {
+        let combined = Htmx::mock();
+        combined.retarget_and_reswap("#list", SwapType::BeforeEnd);
+        let separate = Htmx::mock();
+        separate.retarget("#list");
+        separate.reswap(SwapType::BeforeEnd);
+        assert_eq!(combined.into_response_state(), separate.into_response_state());
+
+        let combined = Htmx::mock();
+        combined.retarget_reswap_reselect("#list", SwapType::OuterHtml, "#item");
+        let separate = Htmx::mock();
+        separate.retarget("#list");
+        separate.reswap(SwapType::OuterHtml);
+        separate.reselect("#item");
+        assert_eq!(combined.into_response_state(), separate.into_response_state());
+    }
+
+    #[test]
+    fn mock_constructors_set_request_state() {
+        let htmx = Htmx::mock();
+        assert!(htmx.is_htmx);
+        assert!(!htmx.boosted);
+        assert!(!htmx.history_restore_request);
+        assert_eq!(htmx.total_trigger_count(), 0);
+        assert_eq!(htmx.into_response_state(), HtmxResponseState::default());
+
+        let htmx = Htmx::mock_with(false, true);
+        assert!(!htmx.is_htmx);
+        assert!(htmx.boosted);
+
+        let htmx = Htmx::mock_from_headers(&[
+            ("hx-request", "true"),
+            ("hx-history-restore-request", "true"),
+            ("hx-trigger", "save"),
+        ]);
+        assert!(htmx.history_restore_request);
+        assert_eq!(htmx.trigger().as_deref(), Some("save"));
+    }
+
+    #[test]
+    fn mock_records_events_without_a_request() {
+        let htmx = Htmx::mock();
+        htmx.trigger_event("created".to_string(), None, None);
+        htmx.redirect("/done");
+        assert_eq!(htmx.get_trigger_names(TriggerType::Standard), ["created"]);
+        assert!(htmx.has_redirect());
+    }
+
+    #[test]
+    fn trigger_event_once_keeps_first_payload() {
+        let htmx = Htmx::mock();
+        htmx.trigger_event_once("saved".to_string(), Some("first".to_string()), None);
+        htmx.trigger_event_once("saved".to_string(), Some("second".to_string()), None);
+
+        assert!(htmx.has_trigger("saved", TriggerType::Standard));
+        assert!(!htmx.has_trigger("saved", TriggerType::AfterSwap));
+        assert_eq!(
+            htmx.get_triggers(TriggerType::Standard).get("saved"),
+            Some(&Some("first".to_string()))
+        );
+    }
+
+    #[actix_web::test]
+    async fn respond_partial_or_full_picks_branch_by_is_htmx() {
+        let htmx = Htmx::mock_with(true, false);
+        assert_eq!(htmx.respond_partial_or_full(|| "partial", || "full"), "partial");
+        let body = htmx
+            .respond_partial_or_full_async(|| async { "partial" }, || async { "full" })
+            .await;
+        assert_eq!(body, "partial");
+
+        let htmx = Htmx::mock_with(false, false);
+        assert_eq!(htmx.respond_partial_or_full(|| "partial", || "full"), "full");
+        let body = htmx
+            .respond_partial_or_full_async(|| async { "partial" }, || async { "full" })
+            .await;
+        assert_eq!(body, "full");
+    }
+
+    #[test]
+    fn request_info_matches_accessors() {
+        let htmx = Htmx::mock_from_headers(&[
+            ("hx-request", "true"),
+            ("hx-boosted", "true"),
+            ("hx-current-url", "https://example.com/todos"),
+            ("hx-prompt", "yes"),
+            ("hx-target", "list"),
+            ("hx-trigger", "save-button"),
+            ("hx-trigger-name", "save"),
+        ]);
+        let info = htmx.request_info();
+        assert_eq!(
+            info,
+            HtmxRequestInfo {
+                is_htmx: htmx.is_htmx,
+                boosted: htmx.boosted,
+                history_restore_request: htmx.history_restore_request,
+                current_url: htmx.current_url(),
+                prompt: htmx.prompt(),
+                target: htmx.target(),
+                trigger: htmx.trigger(),
+                trigger_name: htmx.trigger_name(),
+            }
+        );
+        assert!(info.is_htmx && info.boosted);
+        assert_eq!(info.target.as_deref(), Some("list"));
+
+        let info = Htmx::mock().request_info();
+        assert!(info.prompt.is_none() && info.trigger_name.is_none());
+    }
+
+    #[test]
+    fn request_predicates_cover_every_flag_combination() {
+        for bits in 0..8u8 {
+            let (is_htmx, boosted, restore) = (bits & 1 != 0, bits & 2 != 0, bits & 4 != 0);
+            let htmx = Htmx::mock()
+                .with_is_htmx(is_htmx)
+                .with_boosted(boosted)
+                .with_history_restore(restore);
+
+            assert_eq!(htmx.is_navigating(), is_htmx && !boosted && !restore, "{bits:03b}");
+            assert_eq!(htmx.is_partial_request(), is_htmx && !restore, "{bits:03b}");
+            assert_eq!(htmx.is_ajax_history(), restore, "{bits:03b}");
+            assert_eq!(htmx.is_boosted_navigation(), boosted, "{bits:03b}");
+        }
+    }
+
+    #[test]
+    fn trigger_type_parses_from_header_names() {
+        assert_eq!(TriggerType::try_from("hx-trigger"), Ok(TriggerType::Standard));
+        assert_eq!(
+            TriggerType::try_from("hx-trigger-after-settle"),
+            Ok(TriggerType::AfterSettle)
+        );
+        assert_eq!("hx-trigger-after-swap".parse(), Ok(TriggerType::AfterSwap));
+
+        let err = "hx-retarget".parse::<TriggerType>().unwrap_err();
+        assert_eq!(err, UnknownTriggerType("hx-retarget".to_string()));
+        assert_eq!(err.to_string(), "unknown trigger type: hx-retarget");
+    }
+
+    #[test]
+    fn target_and_trigger_matchers() {
+        let htmx = Htmx::mock_from_headers(&[
+            ("hx-request", "true"),
+            ("hx-target", "list"),
+            ("hx-trigger", "save-button"),
+            ("hx-trigger-name", "save"),
+        ]);
+        assert!(htmx.target_matches("list"));
+        assert!(!htmx.target_matches("detail"));
+        assert!(htmx.target_matches_any(&["detail", "list"]));
+        assert!(!htmx.target_matches_any(&["detail", "sidebar"]));
+        assert!(!htmx.target_matches_any(&[]));
+        assert!(htmx.trigger_matches("save-button"));
+        assert!(!htmx.trigger_matches("cancel-button"));
+        assert!(htmx.trigger_name_matches("save"));
+        assert!(!htmx.trigger_name_matches("cancel"));
+
+        let absent = Htmx::mock();
+        assert!(!absent.target_matches("list"));
+        assert!(!absent.target_matches_any(&["list"]));
+        assert!(!absent.trigger_matches("save-button"));
+        assert!(!absent.trigger_name_matches("save"));
+    }
+
+    #[actix_web::test]
+    async fn triggers_are_written_in_insertion_order() {
+        let headers = response_headers(|htmx| {
+            for name in ["a", "b", "c"] {
+                htmx.trigger_event(name.to_string(), None, None);
+            }
+            htmx.trigger_event("a".to_string(), None, None);
+            assert_eq!(htmx.get_trigger_count(TriggerType::Standard), 3);
+            assert_eq!(htmx.get_trigger_count(TriggerType::AfterSwap), 0);
+        })
+        .await;
+        assert_eq!(headers.get("hx-trigger").unwrap(), "a,b,c");
+
+        let headers = response_headers(|htmx| {
+            for name in ["a", "b", "c"] {
+                htmx.trigger_event(name.to_string(), Some(name.to_uppercase()), None);
+            }
+        })
+        .await;
+        assert_eq!(
+            headers.get("hx-trigger").unwrap(),
+            r#"{"a": "A","b": "B","c": "C"}"#
+        );
+    }
+
+    enum AppEvent {
+        ItemCreated,
+        ItemDeleted,
+    }
+
+    impl HtmxEventName for AppEvent {
+        fn event_name(&self) -> &str {
+            match self {
+                AppEvent::ItemCreated => "item-created",
+                AppEvent::ItemDeleted => "item-deleted",
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn typed_event_names_are_written() {
+        let headers = response_headers(|htmx| {
+            htmx.trigger_typed_event(&AppEvent::ItemCreated, None, None);
+            htmx.trigger_typed_event(&AppEvent::ItemDeleted, None, Some(TriggerType::AfterSwap));
+        })
+        .await;
+        assert_eq!(headers.get("hx-trigger").unwrap(), "item-created");
+        assert_eq!(headers.get("hx-trigger-after-swap").unwrap(), "item-deleted");
+    }
+
+    #[actix_web::test]
+    async fn custom_response_headers_are_written_unless_removed() {
+        let headers = response_headers(|htmx| {
+            htmx.set_response_header("HX-Location", "/todos");
+            htmx.set_response_header("hx-retarget", "#list");
+            assert!(htmx.remove_response_header("HX-Retarget"));
+            assert!(!htmx.remove_response_header("hx-retarget"));
+        })
+        .await;
+        assert_eq!(headers.get("hx-location").unwrap(), "/todos");
+        assert!(headers.get("hx-retarget").is_none());
+
+        let headers = response_headers(|htmx| {
+            htmx.redirect("/new");
+            htmx.set_response_header("hx-location", "/todos");
+            htmx.clear_response_headers();
+        })
+        .await;
+        assert!(headers.get("hx-redirect").is_none());
+        assert!(headers.get("hx-location").is_none());
+    }
+
+    #[actix_web::test]
+    async fn redirect_with_trigger_writes_both_headers() {
+        let headers = response_headers(|htmx| {
+            htmx.redirect_with_trigger("/todos", "saved", None);
+        })
+        .await;
+        assert_eq!(headers.get("hx-redirect").unwrap(), "/todos");
+        assert_eq!(headers.get("hx-trigger").unwrap(), "saved");
+    }
+
+    #[actix_web::test]
+    async fn poll_until_done_switches_between_interval_and_stop() {
+        let headers = response_headers(|htmx| htmx.poll_until_done(false, 500)).await;
+        assert_eq!(headers.get("hx-polling-interval").unwrap(), "500ms");
+        assert!(headers.get("hx-stop-polling").is_none());
+
+        let headers = response_headers(|htmx| htmx.poll_until_done(true, 500)).await;
+        assert_eq!(headers.get("hx-stop-polling").unwrap(), "true");
+        assert!(headers.get("hx-polling-interval").is_none());
+    }
+
+    #[test]
+    fn required_accessors_report_missing_headers() {
+        let absent = Htmx::mock();
+        assert_eq!(absent.current_url_required(), Err(HtmxError::MissingCurrentUrl));
+        assert_eq!(absent.prompt_required(), Err(HtmxError::MissingPrompt));
+        assert_eq!(absent.target_required(), Err(HtmxError::MissingTarget));
+        assert_eq!(absent.trigger_required(), Err(HtmxError::MissingTrigger));
+        assert_eq!(absent.trigger_name_required(), Err(HtmxError::MissingTriggerName));
+
+        let present = Htmx::mock_from_headers(&[
+            ("hx-current-url", "https://example.com/"),
+            ("hx-prompt", "yes"),
+            ("hx-target", "list"),
+            ("hx-trigger", "save-button"),
+            ("hx-trigger-name", "save"),
+        ]);
+        assert_eq!(present.current_url_required().as_deref(), Ok("https://example.com/"));
+        assert_eq!(present.prompt_required().as_deref(), Ok("yes"));
+        assert_eq!(present.target_required().as_deref(), Ok("list"));
+        assert_eq!(present.trigger_required().as_deref(), Ok("save-button"));
+        assert_eq!(present.trigger_name_required().as_deref(), Ok("save"));
+
+        let invalid = Htmx::mock_from_headers(&[("hx-prompt", "café")]);
+        assert_eq!(invalid.prompt(), None);
+        assert_eq!(
+            invalid.prompt_required(),
+            Err(HtmxError::InvalidHeaderValue("hx-prompt".to_string()))
+        );
+        assert_eq!(invalid.target_required(), Err(HtmxError::MissingTarget));
+
+        assert_eq!(
+            actix_web::ResponseError::status_code(&HtmxError::MissingPrompt),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[actix_web::test]
+    async fn reswap_with_scroll_and_show_write_modifier() {
+        let headers =
+            response_headers(|htmx| htmx.reswap_with_scroll(SwapType::InnerHtml, "#top")).await;
+        assert_eq!(headers.get("hx-reswap").unwrap(), "innerHTML scroll:#top");
+
+        let headers =
+            response_headers(|htmx| htmx.reswap_with_show(SwapType::BeforeEnd, "window:bottom")).await;
+        assert_eq!(headers.get("hx-reswap").unwrap(), "beforeend show:window:bottom");
+    }
+
+    #[test]
+    fn get_all_triggers_collects_every_type() {
+        let htmx = Htmx::mock();
+        htmx.trigger_event("saved".to_string(), None, None);
+        htmx.trigger_event("settled".to_string(), Some("1".to_string()), Some(TriggerType::AfterSettle));
+        htmx.trigger_event("swapped".to_string(), None, Some(TriggerType::AfterSwap));
+        htmx.trigger_event("reloaded".to_string(), None, Some(TriggerType::AfterSwap));
+
+        let all = htmx.get_all_triggers();
+        assert_eq!(all.len(), 3);
+        assert!(all[&TriggerType::Standard].contains_key("saved"));
+        assert_eq!(all[&TriggerType::AfterSettle]["settled"], Some("1".to_string()));
+        assert_eq!(
+            all[&TriggerType::AfterSwap].keys().collect::<Vec<_>>(),
+            ["swapped", "reloaded"]
+        );
+        assert_eq!(htmx.total_trigger_count(), 4);
+        assert_eq!(Htmx::mock().total_trigger_count(), 0);
+    }
+
+    #[actix_web::test]
+    async fn ten_simple_triggers_keep_insertion_order() {
+        let headers = response_headers(|htmx| {
+            for i in 0..10 {
+                htmx.trigger_event(format!("event-{i}"), None, None);
+            }
+        })
+        .await;
+        let expected = (0..10).map(|i| format!("event-{i}")).collect::<Vec<_>>().join(",");
+        assert_eq!(headers.get("hx-trigger").unwrap(), expected.as_str());
+    }
+
+    #[test]
+    fn requeued_trigger_keeps_first_position_and_last_payload() {
+        let htmx = Htmx::mock();
+        htmx.trigger_event("a".to_string(), Some("1".to_string()), None);
+        htmx.trigger_event("b".to_string(), None, None);
+        htmx.trigger_event("a".to_string(), Some("2".to_string()), None);
+        htmx.trigger_event("c".to_string(), None, None);
+        htmx.trigger_event("b".to_string(), Some("3".to_string()), None);
+
+        let triggers = htmx.get_triggers(TriggerType::Standard);
+        assert_eq!(triggers.keys().collect::<Vec<_>>(), ["a", "b", "c"]);
+        assert_eq!(triggers["a"], Some("2".to_string()));
+        assert_eq!(triggers["b"], Some("3".to_string()));
+        assert_eq!(triggers["c"], None);
+    }
+
+    #[actix_web::test]
+    async fn suppress_url_changes_writes_both_headers() {
+        let headers = response_headers(|htmx| {
+            assert!(!htmx.is_url_change_suppressed());
+            htmx.suppress_url_changes();
+            assert!(htmx.is_url_change_suppressed());
+        })
+        .await;
+        assert_eq!(headers.get("hx-push-url").unwrap(), "false");
+        assert_eq!(headers.get("hx-replace-url").unwrap(), "false");
+
+        let htmx = Htmx::mock();
+        htmx.disable_push_url();
+        assert!(!htmx.is_url_change_suppressed());
+    }
+
+    #[test]
+    fn compound_trigger_helpers_match_individual_calls() {
+        let combined = Htmx::mock();
+        combined.swap_and_trigger("#list", SwapType::OuterHtml, "updated", Some("done".to_string()));
+        let separate = Htmx::mock();
+        separate.retarget("#list");
+        separate.reswap(SwapType::OuterHtml);
+        separate.trigger_event("updated".to_string(), Some("done".to_string()), None);
+        assert_eq!(combined.into_response_state(), separate.into_response_state());
+
+        let combined = Htmx::mock();
+        combined.refresh_and_trigger("reloaded");
+        let separate = Htmx::mock();
+        separate.refresh();
+        separate.trigger_event("reloaded".to_string(), None, None);
+        assert_eq!(combined.into_response_state(), separate.into_response_state());
+    }
+
+    #[actix_web::test]
+    async fn navigating_away_stops_polling() {
+        let headers = response_headers(|htmx| htmx.redirect_and_stop_polling("/done")).await;
+        assert_eq!(headers.get("hx-stop-polling").unwrap(), "true");
+        assert_eq!(headers.get("hx-redirect").unwrap(), "/done");
+
+        let headers = response_headers(|htmx| htmx.location_and_stop_polling("/done")).await;
+        assert_eq!(headers.get("hx-stop-polling").unwrap(), "true");
+        assert_eq!(headers.get("hx-location").unwrap(), "/done");
+    }
+
+    #[test]
+    fn replace_trigger_payload_keeps_position() {
+        let htmx = Htmx::mock();
+        htmx.trigger_event("a".to_string(), Some("old".to_string()), None);
+        htmx.trigger_event("b".to_string(), None, None);
+
+        assert!(htmx.replace_trigger_payload("a", TriggerType::Standard, Some("new".to_string())));
+        assert!(!htmx.replace_trigger_payload("a", TriggerType::AfterSwap, None));
+        assert!(!htmx.replace_trigger_payload("missing", TriggerType::Standard, None));
+
+        let triggers = htmx.get_triggers(TriggerType::Standard);
+        assert_eq!(triggers.get_index(0), Some((&"a".to_string(), &Some("new".to_string()))));
+        assert_eq!(triggers.len(), 2);
+    }
+
+    /// Drops the `internal-*` events a handler queued before the stock middleware writes them.
+    async fn drop_internal_triggers(
+        req: ServiceRequest,
+        next: actix_web::middleware::Next<impl actix_web::body::MessageBody>,
+    ) -> Result<actix_web::dev::ServiceResponse<impl actix_web::body::MessageBody>, Error> {
+        let res = next.call(req).await?;
+        if let Some(htmx) = res.request().extensions().get::<Htmx>() {
+            let mut state = htmx.take_response_state();
+            state.standard_triggers.retain(|name, _| !name.starts_with("internal-"));
+            htmx.apply_response_state(state);
+        }
+        Ok(res)
+    }
+
+    #[actix_web::test]
+    async fn custom_middleware_can_filter_response_state() {
+        let app = test::init_service(
+            App::new()
+                .wrap(actix_web::middleware::from_fn(drop_internal_triggers))
+                .wrap(crate::HtmxMiddleware)
+                .route(
+                    "/",
+                    web::get().to(|htmx: Htmx| async move {
+                        htmx.trigger_event("internal-audit".to_string(), None, None);
+                        htmx.trigger_event("saved".to_string(), None, None);
+                        htmx.redirect("/todos");
+                        HttpResponse::Ok().finish()
+                    }),
+                ),
+        )
+        .await;
+        let req = TestRequest::get().uri("/").insert_header(("hx-request", "true"));
+        let res = test::call_service(&app, req.to_request()).await;
+        assert_eq!(res.headers().get("hx-trigger").unwrap(), "saved");
+        assert_eq!(res.headers().get("hx-redirect").unwrap(), "/todos");
+    }
+
+    #[test]
+    fn take_response_state_leaves_nothing_behind() {
+        let htmx = Htmx::mock();
+        htmx.trigger_event("saved".to_string(), None, None);
+        htmx.refresh();
+
+        let state = htmx.take_response_state();
+        assert_eq!(state.standard_triggers.len(), 1);
+        assert_eq!(state.response_headers["hx-refresh"], "true");
+        assert_eq!(htmx.total_trigger_count(), 0);
+        assert!(!htmx.has_refresh());
+
+        htmx.apply_response_state(state.clone());
+        assert_eq!(htmx.into_response_state(), state);
+    }
+
+    #[test]
+    fn apply_response_state_lowercases_ascii_header_names() {
+        let htmx = Htmx::mock();
+        let mut state = HtmxResponseState::default();
+        state.response_headers.insert("HX-Retarget".to_string(), "#list".to_string());
+        htmx.apply_response_state(state);
+
+        assert_eq!(htmx.get_response_header("hx-retarget").as_deref(), Some("#list"));
+    }
+
+    #[test]
+    fn htmx_version_at_least_parses_major_minor() {
+        let version = |v: &str| Htmx::mock_from_headers(&[("hx-request", "true"), ("hx-version", v)]);
+
+        let htmx = version("2.0.0");
+        assert_eq!(htmx.htmx_version().as_deref(), Some("2.0.0"));
+        assert!(htmx.htmx_version_at_least(2, 0));
+        assert!(htmx.htmx_version_at_least(1, 9));
+        assert!(!htmx.htmx_version_at_least(2, 1));
+
+        assert!(version("1.9.12").htmx_version_at_least(1, 9));
+        assert!(!version("1.9.12").htmx_version_at_least(2, 0));
+        assert!(!version("two").htmx_version_at_least(0, 0));
+        assert!(!version("2").htmx_version_at_least(0, 0));
+        assert!(!Htmx::mock().htmx_version_at_least(0, 0));
+    }
+
+    #[test]
+    fn form_heuristics_read_trigger_headers() {
+        let form = Htmx::mock_from_headers(&[
+            ("hx-request", "true"),
+            ("hx-trigger", "signup-form"),
+            ("hx-trigger-name", "signup"),
+        ]);
+        assert!(form.is_form_submission());
+        assert!(form.has_form_values());
+
+        let named_only = Htmx::mock_from_headers(&[("hx-request", "true"), ("hx-trigger-name", "q")]);
+        assert!(named_only.is_form_submission());
+        assert!(!named_only.has_form_values());
+
+        let button = Htmx::mock_from_headers(&[("hx-request", "true"), ("hx-trigger", "load-more")]);
+        assert!(!button.is_form_submission());
+        assert!(!button.has_form_values());
+
+        assert!(!named_only.as_non_htmx().is_form_submission());
+    }
+
+    #[test]
+    fn get_header_raw_reads_any_request_header() {
+        let htmx = Htmx::mock_from_headers(&[("hx-request", "true"), ("HX-Vals", r#"{"key":"value"}"#)]);
+        assert_eq!(htmx.get_header_raw("hx-vals").as_deref(), Some(r#"{"key":"value"}"#));
+        assert_eq!(htmx.get_header_raw("HX-Vals").as_deref(), Some(r#"{"key":"value"}"#));
+        assert_eq!(htmx.get_header_raw("hx-missing"), None);
+
+        let htmx = Htmx::mock_from_headers(&[("hx-request", "true"), ("authorization", "Bearer secret")]);
+        assert_eq!(htmx.get_header_raw("authorization"), None);
+
+        let req = TestRequest::default().insert_header(("hx2-vals", "{}")).to_srv_request();
+        assert_eq!(Htmx::with_header_prefix(&req, Some("hx2")).get_header_raw("hx-vals").as_deref(), Some("{}"));
+
+        let req = TestRequest::default()
+            .insert_header(("hx-vals", HeaderValue::from_bytes(b"caf\xe9").unwrap()))
+            .to_srv_request();
+        assert_eq!(Htmx::new(&req).get_header_raw("hx-vals"), None);
+    }
+
+    #[test]
+    fn debug_output_omits_request_headers() {
+        let htmx = Htmx::mock_from_headers(&[("hx-request", "true"), ("cookie", "session=secret")]);
+        let output = format!("{:?}", htmx);
+        assert!(output.contains("is_htmx: true"), "{output}");
+        assert!(!output.contains("secret"), "{output}");
+    }
+
+    #[test]
+    fn is_first_load_for_full_page_requests() {
+        let plain = Htmx::mock().with_is_htmx(false);
+        assert!(plain.is_first_load());
+        assert!(!plain.is_history_restore());
+
+        let restore = Htmx::mock().with_history_restore(true);
+        assert!(restore.is_first_load());
+        assert!(restore.is_history_restore());
+
+        let partial = Htmx::mock();
+        assert!(!partial.is_first_load());
+        assert!(!partial.with_boosted(true).is_first_load());
+    }
+
+    #[test]
+    fn option_accessors_fall_back_when_absent() {
+        let present = Htmx::mock_from_headers(&[
+            ("hx-current-url", "https://example.com/"),
+            ("hx-prompt", "yes"),
+            ("hx-target", "list"),
+            ("hx-trigger", "save-button"),
+            ("hx-trigger-name", "save"),
+        ]);
+        let absent = Htmx::mock();
+
+        assert_eq!(present.current_url_or("/"), "https://example.com/");
+        assert_eq!(absent.current_url_or("/"), "/");
+        assert_eq!(present.prompt_or_default(), "yes");
+        assert_eq!(absent.prompt_or_default(), "");
+        assert_eq!(present.target_or_else(|| "body".to_string()), "list");
+        assert_eq!(absent.target_or_else(|| "body".to_string()), "body");
+        assert_eq!(present.trigger_or("none"), "save-button");
+        assert_eq!(absent.trigger_or_default(), "");
+        assert_eq!(present.trigger_name_or_else(String::new), "save");
+        assert_eq!(absent.trigger_name_or(String::from("anonymous")), "anonymous");
+    }
+
+    #[actix_web::test]
+    async fn as_non_htmx_shares_response_state() {
+        let headers = response_headers(|htmx| {
+            let full_page = htmx.as_non_htmx();
+            assert!(!full_page.is_htmx && !full_page.boosted && !full_page.history_restore_request);
+            assert!(htmx.is_htmx);
+
+            full_page.trigger_event("rendered".to_string(), None, None);
+            htmx.push_url("/todos");
+            assert!(full_page.has_response_header("hx-push-url"));
+            assert!(htmx.has_trigger("rendered", TriggerType::Standard));
+        })
+        .await;
+        assert_eq!(headers.get("hx-trigger").unwrap(), "rendered");
+        assert_eq!(headers.get("hx-push-url").unwrap(), "/todos");
+    }
+
+    #[test]
+    fn mock_setters_override_single_flags() {
+        let htmx = Htmx::mock().with_boosted(true);
+        assert!(htmx.is_htmx && htmx.boosted && !htmx.history_restore_request);
+
+        let htmx = Htmx::mock().with_history_restore(true).with_is_htmx(false);
+        assert!(!htmx.is_htmx && !htmx.boosted && htmx.history_restore_request);
+
+        let htmx = Htmx::mock_with(false, true).with_is_htmx(true).with_boosted(false);
+        assert!(htmx.is_htmx && !htmx.boosted && !htmx.history_restore_request);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn response_as_json_embeds_headers_and_triggers() {
+        let htmx = Htmx::mock();
+        htmx.redirect("/todos");
+        htmx.trigger_event("saved".to_string(), None, None);
+        htmx.trigger_event("toast".to_string(), Some(r#"{"level": "info"}"#.to_string()), None);
+        htmx.trigger_event("settled".to_string(), Some("done".to_string()), Some(TriggerType::AfterSettle));
+
+        assert_eq!(
+            htmx.response_as_json(),
+            serde_json::json!({
+                "headers": {"hx-redirect": "/todos"},
+                "triggers": {
+                    "standard": {"saved": null, "toast": {"level": "info"}},
+                    "afterSettle": {"settled": "done"},
+                    "afterSwap": {},
+                },
+            })
+        );
+    }
+
+    #[actix_web::test]
+    async fn send_no_content_and_trigger_is_204_with_event() {
+        let app = test::init_service(App::new().wrap(crate::HtmxMiddleware).route(
+            "/",
+            web::post().to(|htmx: Htmx| async move { htmx.send_no_content_and_trigger("saved") }),
+        ))
+        .await;
+        let req = TestRequest::post().uri("/").insert_header(("hx-request", "true"));
+        let res = test::call_service(&app, req.to_request()).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert_eq!(res.headers().get("hx-trigger").unwrap(), "saved");
+        assert!(test::read_body(res).await.is_empty());
+    }
+
+    fn set_everything_if(htmx: &Htmx, condition: bool) {
+        htmx.trigger_event_if(condition, "saved".to_string(), None, None);
+        htmx.redirect_if(condition, "/todos");
+        htmx.refresh_if(condition);
+        htmx.stop_polling_if(condition);
+        htmx.push_url_if(condition, "/pushed");
+        htmx.replace_url_if(condition, "/replaced");
+        htmx.reswap_if(condition, SwapType::OuterHtml);
+        htmx.retarget_if(condition, "#list");
+    }
+
+    const CONDITIONAL_HEADERS: [&str; 8] = [
+        "hx-trigger",
+        "hx-redirect",
+        "hx-refresh",
+        "hx-stop-polling",
+        "hx-push-url",
+        "hx-replace-url",
+        "hx-reswap",
+        "hx-retarget",
+    ];
+
+    #[actix_web::test]
+    async fn conditional_setters_only_write_when_true() {
+        let headers = response_headers(|htmx| set_everything_if(htmx, false)).await;
+        for name in CONDITIONAL_HEADERS {
+            assert!(headers.get(name).is_none(), "{name}");
+        }
+
+        let headers = response_headers(|htmx| set_everything_if(htmx, true)).await;
+        for name in CONDITIONAL_HEADERS {
+            assert!(headers.get(name).is_some(), "{name}");
+        }
+        assert_eq!(headers.get("hx-retarget").unwrap(), "#list");
+    }
+
+    #[actix_web::test]
+    #[cfg(feature = "serde")]
+    async fn response_state_round_trips_through_json() {
+        let htmx = Htmx::mock();
+        htmx.trigger_event("saved".to_string(), Some(r#"{"id": 1}"#.to_string()), None);
+        htmx.trigger_event("settled".to_string(), None, Some(TriggerType::AfterSettle));
+        htmx.push_url("/todos/1");
+        let json = serde_json::to_string(&htmx.into_response_state()).unwrap();
+
+        let app = test::init_service(App::new().wrap(crate::HtmxMiddleware).route(
+            "/",
+            web::get().to(move |htmx: Htmx| {
+                let state: HtmxResponseState = serde_json::from_str(&json).unwrap();
+                htmx.apply_response_state(state);
+                async { HttpResponse::Ok().finish() }
+            }),
+        ))
+        .await;
+        let req = TestRequest::get().uri("/").insert_header(("hx-request", "true"));
+        let headers = test::call_service(&app, req.to_request()).await.headers().clone();
+
+        assert_eq!(headers.get("hx-trigger").unwrap(), r#"{"saved": {"id": 1}}"#);
+        assert_eq!(headers.get("hx-trigger-after-settle").unwrap(), "settled");
+        assert_eq!(headers.get("hx-push-url").unwrap(), "/todos/1");
+    }
+
+    #[test]
+    fn validate_response_reports_each_conflict() {
+        let clean = Htmx::mock();
+        clean.redirect("/todos");
+        clean.push_url("/todos");
+        assert_eq!(clean.validate_response(), []);
+
+        let htmx = Htmx::mock();
+        htmx.redirect("/todos");
+        htmx.set_response_header("HX-Location", "/todos");
+        assert_eq!(htmx.validate_response(), [HtmxResponseWarning::ConflictingRedirectHeaders]);
+
+        let htmx = Htmx::mock();
+        htmx.refresh();
+        htmx.push_url("/todos");
+        assert_eq!(htmx.validate_response(), [HtmxResponseWarning::RefreshWithPushUrl]);
+
+        let htmx = Htmx::mock();
+        htmx.refresh();
+        htmx.replace_url("/todos");
+        assert_eq!(htmx.validate_response(), [HtmxResponseWarning::RefreshWithReplaceUrl]);
+
+        htmx.push_url("/todos");
+        htmx.redirect("/todos");
+        htmx.set_response_header("HX-Location", "/todos");
+        assert_eq!(
+            htmx.validate_response(),
+            [
+                HtmxResponseWarning::ConflictingRedirectHeaders,
+                HtmxResponseWarning::RefreshWithPushUrl,
+                HtmxResponseWarning::RefreshWithReplaceUrl,
+            ]
+        );
+        assert_eq!(
+            HtmxResponseWarning::RefreshWithPushUrl.to_string(),
+            "HX-Push-Url is set alongside HX-Refresh"
+        );
+    }
+
+    #[test]
+    fn diagnostics_count_queued_state() {
+        let htmx = Htmx::mock();
+        let before = htmx.diagnostics();
+        assert_eq!(
+            (
+                before.standard_trigger_count,
+                before.after_settle_trigger_count,
+                before.after_swap_trigger_count,
+                before.response_header_count
+            ),
+            (0, 0, 0, 0)
+        );
+        assert!(before.is_htmx);
+
+        htmx.trigger_event("saved".to_string(), None, None);
+        htmx.trigger_event("toast".to_string(), None, None);
+        htmx.trigger_event("settled".to_string(), None, Some(TriggerType::AfterSettle));
+        htmx.trigger_event("swapped".to_string(), None, Some(TriggerType::AfterSwap));
+        htmx.redirect("/todos");
+
+        let after = htmx.diagnostics();
+        assert_eq!(after.standard_trigger_count, 2);
+        assert_eq!(after.after_settle_trigger_count, 1);
+        assert_eq!(after.after_swap_trigger_count, 1);
+        assert_eq!(after.response_header_count, 1);
+        assert_eq!(
+            after.to_string(),
+            "is_htmx: true, triggers: 2 standard, 1 after settle, 1 after swap, response headers: 1"
+        );
+    }
+
+    #[test]
+    fn swap_and_trigger_types_hash_compare_and_clone() {
+        let mut swaps = std::collections::HashSet::new();
+        swaps.insert(SwapType::OuterHtml);
+        swaps.insert(SwapType::OuterHtml);
+        assert_eq!(swaps.len(), 1);
+        assert!(swaps.contains(&SwapType::OuterHtml));
+        assert_eq!(SwapType::InnerHtml, SwapType::InnerHtml);
+        assert_ne!(SwapType::InnerHtml, SwapType::OuterHtml);
+        assert_eq!(SwapType::default(), SwapType::InnerHtml);
+
+        let swap_type = SwapType::BeforeEnd;
+        let htmx = Htmx::mock();
+        htmx.reswap(swap_type.clone());
+        assert_eq!(htmx.get_response_header("hx-reswap").as_deref(), Some(swap_type.as_ref()));
+
+        let triggers = [TriggerType::Standard, TriggerType::AfterSwap, TriggerType::Standard]
+            .into_iter()
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(triggers.len(), 2);
+        let trigger_type = TriggerType::AfterSettle;
+        assert_eq!(trigger_type.clone(), trigger_type);
+    }
+
+    #[test]
+    fn retarget_accepts_selectors_and_strings() {
+        let typed = Htmx::mock();
+        typed.retarget(CssSelector::new("#my-div"));
+        let plain = Htmx::mock();
+        plain.retarget("#my-div");
+        assert_eq!(typed.into_response_state(), plain.into_response_state());
+
+        let htmx = Htmx::mock();
+        htmx.reselect(CssSelector::from(String::from("#item")));
+        assert_eq!(htmx.get_response_header("hx-reselect").as_deref(), Some("#item"));
+    }
+
+    #[derive(Default)]
+    struct PageContext {
+        htmx: Htmx,
+        title: String,
+    }
+
+    #[test]
+    fn default_htmx_is_non_htmx_and_empty() {
+        let context = PageContext::default();
+        assert!(context.title.is_empty());
+        assert!(!context.htmx.is_htmx);
+        assert!(!context.htmx.boosted);
+        assert!(!context.htmx.history_restore_request);
+        assert_eq!(context.htmx.target(), None);
+        assert_eq!(context.htmx.total_trigger_count(), 0);
+        assert_eq!(context.htmx.diagnostics().response_header_count, 0);
+    }
+}
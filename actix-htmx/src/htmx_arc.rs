@@ -0,0 +1,165 @@
+use actix_web::dev::{Payload, ServiceRequest};
+use actix_web::error::{Error, ErrorBadRequest, ErrorInternalServerError};
+use actix_web::http::header::{self, HeaderMap};
+use actix_web::{FromRequest, HttpMessage, HttpRequest, HttpResponse, Responder};
+use futures_util::future::{ready, Ready};
+use indexmap::IndexMap;
+use log::{log, warn, Level};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::mem;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::headers::{RequestHeaders, ResponseHeaders};
+use crate::htmx::{HtmxEventName, HtmxInner};
+use crate::{
+    CssSelector, HtmxChain, HtmxDiagnostics, HtmxError, HtmxEventSpec, HtmxRequestInfo, HtmxResponseState,
+    HtmxResponseWarning, HxOobSpec, HxReswap, HxUrl, RedirectError, SwapModifier, SwapType, TriggerType,
+};
+
+/// A `Send + Sync` version of [`crate::Htmx`] for handlers that hand it to spawned tasks,
+/// inserted by [`crate::HtmxArcMiddleware`]. It has the same methods as [`crate::Htmx`].
+#[derive(Clone)]
+pub struct HtmxArc {
+    inner: Arc<Mutex<HtmxInner>>,
+    pub is_htmx: bool,
+    pub boosted: bool,
+    pub history_restore_request: bool,
+}
+
+impl HtmxArc {
+    fn from_inner(inner: HtmxInner) -> HtmxArc {
+        let is_htmx = inner.get_bool_header(RequestHeaders::HX_REQUEST);
+        let boosted = inner.get_bool_header(RequestHeaders::HX_BOOSTED);
+        let history_restore_request = inner.get_bool_header(RequestHeaders::HX_HISTORY_RESTORE_REQUEST);
+
+        HtmxArc {
+            inner: Arc::new(Mutex::new(inner)),
+            is_htmx,
+            boosted,
+            history_restore_request,
+        }
+    }
+
+    pub fn new(req: &ServiceRequest) -> HtmxArc {
+        HtmxArc::from_inner(HtmxInner::new(req.request()))
+    }
+
+    pub(crate) fn with_header_prefix(req: &ServiceRequest, prefix: Option<&str>) -> HtmxArc {
+        HtmxArc::from_inner(HtmxInner::from_headers_with_prefix(req.headers(), prefix))
+    }
+
+    pub(crate) fn log_to(&self, target: &'static str, level: Level) {
+        self.lock_inner().log_to(target, level);
+    }
+
+    pub(crate) fn lock_inner(&self) -> MutexGuard<'_, HtmxInner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn read_inner(&self) -> MutexGuard<'_, HtmxInner> {
+        self.lock_inner()
+    }
+
+    fn write_inner(&self) -> MutexGuard<'_, HtmxInner> {
+        self.lock_inner()
+    }
+
+    crate::methods::htmx_methods!();
+}
+
+impl Default for HtmxArc {
+    fn default() -> Self {
+        HtmxArc::from_inner(HtmxInner::from_headers(&HeaderMap::new()))
+    }
+}
+
+impl fmt::Debug for HtmxArc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.lock_inner();
+        f.debug_struct("HtmxArc")
+            .field("is_htmx", &self.is_htmx)
+            .field("boosted", &self.boosted)
+            .field("history_restore_request", &self.history_restore_request)
+            .field("response_headers", &inner.response_headers)
+            .field("standard_trigger_count", &inner.triggers(&TriggerType::Standard).len())
+            .field("after_settle_trigger_count", &inner.triggers(&TriggerType::AfterSettle).len())
+            .field("after_swap_trigger_count", &inner.triggers(&TriggerType::AfterSwap).len())
+            .finish()
+    }
+}
+
+impl FromRequest for HtmxArc {
+    type Error = Error;
+    type Future = Ready<Result<HtmxArc, Error>>;
+
+    /// Fails with a 500 when [`crate::HtmxArcMiddleware`] isn't wrapping the route, since
+    /// nothing would write the headers and triggers queued on the extracted value.
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if let Some(htmx) = req.extensions().get::<HtmxArc>() {
+            return ready(Ok(htmx.clone()));
+        }
+
+        warn!("HtmxArc extracted without HtmxArcMiddleware: {}", req.path());
+        ready(Err(ErrorInternalServerError("HtmxArc requires HtmxArcMiddleware")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Htmx;
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn debug_output_omits_request_headers() {
+        let req = TestRequest::default()
+            .insert_header(("hx-request", "true"))
+            .insert_header(("cookie", "session=SECRET"))
+            .insert_header(("authorization", "Bearer TOKEN"))
+            .to_srv_request();
+        let htmx = HtmxArc::new(&req);
+        htmx.refresh();
+
+        let inner_debug = format!("{:?}", htmx.lock_inner());
+        let debug = format!("{:?} {}", htmx, inner_debug);
+        assert!(debug.contains("hx-refresh"));
+        assert!(!debug.contains("SECRET"));
+        assert!(!debug.contains("TOKEN"));
+    }
+
+    #[actix_web::test]
+    async fn extract_without_middleware_fails() {
+        let req = TestRequest::default().insert_header(("hx-request", "true")).to_http_request();
+        let err = HtmxArc::extract(&req).await.unwrap_err();
+        assert_eq!(err.as_response_error().status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn shares_the_htmx_api() {
+        let headers = [("hx-request", "true"), ("hx-target", "list"), ("hx-trigger-name", "q")];
+        let htmx = Htmx::mock_from_headers(&headers);
+        let arc = HtmxArc::mock_from_headers(&headers);
+
+        assert_eq!(htmx.request_info(), arc.request_info());
+        assert_eq!(htmx.target_or_default(), arc.target_or_default());
+        assert_eq!(htmx.is_form_submission(), arc.is_form_submission());
+        assert!(arc.redirect_path("//evil.example").is_err());
+
+        htmx.chain().retarget("#list").reswap(SwapType::BeforeEnd).apply();
+        arc.chain().retarget("#list").reswap(SwapType::BeforeEnd).apply();
+        htmx.redirect_with_trigger("/done", "saved", None);
+        arc.redirect_with_trigger("/done", "saved", None);
+        htmx.suppress_url_changes();
+        arc.suppress_url_changes();
+        htmx.trigger_event_once("saved".to_string(), Some("ignored".to_string()), None);
+        arc.trigger_event_once("saved".to_string(), Some("ignored".to_string()), None);
+
+        assert_eq!(htmx.validate_response(), arc.validate_response());
+        assert_eq!(htmx.diagnostics(), arc.diagnostics());
+        assert_eq!(htmx.into_response_state(), arc.into_response_state());
+    }
+}
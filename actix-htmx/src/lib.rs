@@ -37,16 +37,76 @@
 //!         Some(TriggerType::Standard)
 //!     );
 //!
-//!     HttpResponse::Ok().content_type("text/html").body(// render the view)
-//!
+//!     HttpResponse::Ok().content_type("text/html").body(/* render the view */ "")
 //! }
 //! ```
 
+mod config;
+mod error;
+mod ext;
+mod guard;
 mod headers;
 mod htmx;
+mod htmx_arc;
+mod methods;
+mod metrics;
 mod middleware;
+mod response;
+mod reswap;
+mod selector;
+mod template;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
+mod url;
 
 pub use self::{
-    htmx::{Htmx, TriggerType},
-    middleware::HtmxMiddleware,
+    config::{HtmxConfig, HtmxRouteConfig},
+    error::HtmxError,
+    ext::HtmxRequestExt,
+    guard::{HtmxGuard, NonHtmxGuard},
+    htmx::{
+        Htmx, HtmxDiagnostics, HtmxEventName, HtmxEventSpec, HtmxRequestInfo, HtmxResponseState,
+        HtmxResponseWarning, HxOobSpec, RedirectError, SwapModifier, SwapType, TriggerType,
+        UnknownSwapType, UnknownTriggerType,
+    },
+    htmx_arc::HtmxArc,
+    metrics::{HtmxMetrics, NoopMetrics},
+    middleware::{
+        ConfiguredHtmxMiddleware, HeaderErrorHandler, HtmxArcMiddleware, HtmxMiddleware,
+        HtmxMiddlewareConfig, HtmxOnlyMiddleware, RequestFilter,
+    },
+    response::{HtmxChain, HtmxOperation, HtmxResponse},
+    reswap::HxReswap,
+    selector::CssSelector,
+    template::{HtmxAwareTemplate, HtmxTemplateExt},
+    url::{HxUrl, HxUrlError},
 };
+
+/// Internals exposed for the benchmarks only; not part of the public API.
+#[doc(hidden)]
+pub mod __bench {
+    pub use crate::middleware::{simple_header, trigger_json};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{self, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+
+    #[actix_web::test]
+    async fn stop_polling_writes_header() {
+        let app = test::init_service(App::new().wrap(HtmxMiddleware).route(
+            "/poll",
+            web::get().to(|htmx: Htmx| async move {
+                htmx.stop_polling();
+                HttpResponse::Ok().finish()
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::get().uri("/poll").insert_header(("hx-request", "true"));
+        let res = test::call_service(&app, req.to_request()).await;
+        assert_eq!(res.headers().get("hx-stop-polling").unwrap(), "true");
+    }
+}
@@ -0,0 +1,986 @@
+//! The request accessors and response setters shared by [`crate::Htmx`] and
+//! [`crate::HtmxArc`]. Both types expand [`htmx_methods`] in their own `impl` block, so
+//! the two APIs can't drift apart.
+
+/// Expands to the shared methods. The implementing type needs `from_inner`, `read_inner`
+/// and `write_inner`, an `inner` field and the three public request flags. `read_inner`
+/// may be a lock, so a method must never call another while holding its guard.
+macro_rules! htmx_methods {
+    () => {
+            /// A view of the same request with every htmx flag cleared. Response state is shared, so
+            /// anything queued through either value is still written.
+            pub fn as_non_htmx(&self) -> Self {
+                Self {
+                    inner: self.inner.clone(),
+                    is_htmx: false,
+                    boosted: false,
+                    history_restore_request: false,
+                }
+            }
+
+            #[cfg(any(test, feature = "test-utils"))]
+            pub fn mock() -> Self {
+                Self::mock_with(true, false)
+            }
+
+            #[cfg(any(test, feature = "test-utils"))]
+            pub fn mock_with(is_htmx: bool, boosted: bool) -> Self {
+                Self::mock_from_headers(&[
+                    (RequestHeaders::HX_REQUEST, is_htmx.to_string()),
+                    (RequestHeaders::HX_BOOSTED, boosted.to_string()),
+                ])
+            }
+
+            #[cfg(any(test, feature = "test-utils"))]
+            pub fn mock_from_headers<K, V>(headers: &[(K, V)]) -> Self
+            where
+                K: AsRef<str>,
+                V: AsRef<str>,
+            {
+                let mut header_map = HeaderMap::new();
+                headers.iter().for_each(|(key, value)| {
+                    match (
+                        header::HeaderName::try_from(key.as_ref()),
+                        header::HeaderValue::from_str(value.as_ref()),
+                    ) {
+                        (Ok(key), Ok(value)) => {
+                            header_map.insert(key, value);
+                        }
+                        _ => log::warn!("Ignoring invalid mock header: {}", key.as_ref()),
+                    }
+                });
+                Self::from_inner(HtmxInner::from_headers(&header_map))
+            }
+
+            #[cfg(any(test, feature = "test-utils"))]
+            pub fn with_is_htmx(mut self, is_htmx: bool) -> Self {
+                self.is_htmx = is_htmx;
+                self
+            }
+
+            #[cfg(any(test, feature = "test-utils"))]
+            pub fn with_boosted(mut self, boosted: bool) -> Self {
+                self.boosted = boosted;
+                self
+            }
+
+            #[cfg(any(test, feature = "test-utils"))]
+            pub fn with_history_restore(mut self, history_restore_request: bool) -> Self {
+                self.history_restore_request = history_restore_request;
+                self
+            }
+
+            pub fn current_url(&self) -> Option<String> {
+                self.read_inner().get_string_header(RequestHeaders::HX_CURRENT_URL)
+            }
+
+            /// The path of `HX-Current-URL`, or the raw value if it isn't an absolute url. This and the
+            /// other `current_url_*` helpers need the `url-parsing` feature.
+            #[cfg(feature = "url-parsing")]
+            pub fn current_url_path(&self) -> Option<String> {
+                let current_url = self.current_url()?;
+                match ::url::Url::parse(&current_url) {
+                    Ok(url) => Some(url.path().to_string()),
+                    Err(_) => Some(current_url),
+                }
+            }
+
+            #[cfg(feature = "url-parsing")]
+            pub fn current_url_query(&self) -> Option<String> {
+                let current_url = self.current_url()?;
+                match ::url::Url::parse(&current_url) {
+                    Ok(url) => url.query().map(str::to_string),
+                    Err(_) => current_url
+                        .split_once('?')
+                        .map(|(_, query)| query.split('#').next().unwrap_or_default().to_string()),
+                }
+            }
+
+            /// Whether the path of `HX-Current-URL` equals `pattern` or ends with it on a `/` boundary,
+            /// so `"users"` matches `/admin/users` but not `/superusers`. An empty pattern never matches.
+            #[cfg(feature = "url-parsing")]
+            pub fn current_url_matches(&self, pattern: &str) -> bool {
+                if pattern.is_empty() {
+                    return false;
+                }
+                self.current_url_path().is_some_and(|path| match path.strip_suffix(pattern) {
+                    Some(rest) => rest.is_empty() || rest.ends_with('/') || pattern.starts_with('/'),
+                    None => false,
+                })
+            }
+
+            /// Whether the path of `HX-Current-URL` starts with `prefix` on a `/` boundary, so
+            /// `"/admin"` matches `/admin` and `/admin/users` but not `/administrator`.
+            #[cfg(feature = "url-parsing")]
+            pub fn current_url_starts_with(&self, prefix: &str) -> bool {
+                if prefix.is_empty() {
+                    return false;
+                }
+                self.current_url_path().is_some_and(|path| match path.strip_prefix(prefix) {
+                    Some(rest) => rest.is_empty() || rest.starts_with('/') || prefix.ends_with('/'),
+                    None => false,
+                })
+            }
+
+            #[cfg(feature = "url-parsing")]
+            pub fn current_url_is_root(&self) -> bool {
+                self.current_url_path().is_some_and(|path| path == "/")
+            }
+
+            pub fn prompt(&self) -> Option<String> {
+                self.read_inner().get_string_header(RequestHeaders::HX_PROMPT)
+            }
+
+            pub fn target(&self) -> Option<String> {
+                self.read_inner().get_string_header(RequestHeaders::HX_TARGET)
+            }
+
+            pub fn trigger(&self) -> Option<String> {
+                self.read_inner().get_string_header(RequestHeaders::HX_TRIGGER)
+            }
+
+            pub fn trigger_name(&self) -> Option<String> {
+                self.read_inner().get_string_header(RequestHeaders::HX_TRIGGER_NAME)
+            }
+
+            /// Experimental: the `HX-Version` header, which only some htmx builds send.
+            pub fn htmx_version(&self) -> Option<String> {
+                self.read_inner().get_string_header(RequestHeaders::HX_VERSION)
+            }
+
+            /// Experimental: `false` when `HX-Version` is absent or not a `major.minor[.patch]` version.
+            pub fn htmx_version_at_least(&self, major: u32, minor: u32) -> bool {
+                let Some(version) = self.htmx_version() else {
+                    return false;
+                };
+                let mut parts = version.trim().split('.').map(str::parse::<u32>);
+                match (parts.next(), parts.next()) {
+                    (Some(Ok(version_major)), Some(Ok(version_minor))) => {
+                        (version_major, version_minor) >= (major, minor)
+                    }
+                    _ => false,
+                }
+            }
+
+            /// Any `HX-*` request header by name, e.g. `HX-Vals` from an htmx extension. `None` when
+            /// absent, not valid UTF-8, or not an htmx header: other request headers aren't kept.
+            pub fn get_header_raw(&self, name: &str) -> Option<String> {
+                self.read_inner()
+                    .raw_request_headers
+                    .iter()
+                    .find(|(raw_name, _)| raw_name.eq_ignore_ascii_case(name))
+                    .and_then(|(_, value)| value.to_str().ok())
+                    .map(str::to_string)
+            }
+
+            $crate::htmx::option_accessors! {
+                current_url => current_url_or, current_url_or_default, current_url_or_else;
+                prompt => prompt_or, prompt_or_default, prompt_or_else;
+                target => target_or, target_or_default, target_or_else;
+                trigger => trigger_or, trigger_or_default, trigger_or_else;
+                trigger_name => trigger_name_or, trigger_name_or_default, trigger_name_or_else;
+            }
+
+            pub fn current_url_required(&self) -> Result<String, HtmxError> {
+                self.current_url().ok_or_else(|| {
+                    self.required_header_error(RequestHeaders::HX_CURRENT_URL, HtmxError::MissingCurrentUrl)
+                })
+            }
+
+            pub fn prompt_required(&self) -> Result<String, HtmxError> {
+                self.prompt().ok_or_else(|| {
+                    self.required_header_error(RequestHeaders::HX_PROMPT, HtmxError::MissingPrompt)
+                })
+            }
+
+            pub fn target_required(&self) -> Result<String, HtmxError> {
+                self.target().ok_or_else(|| {
+                    self.required_header_error(RequestHeaders::HX_TARGET, HtmxError::MissingTarget)
+                })
+            }
+
+            pub fn trigger_required(&self) -> Result<String, HtmxError> {
+                self.trigger().ok_or_else(|| {
+                    self.required_header_error(RequestHeaders::HX_TRIGGER, HtmxError::MissingTrigger)
+                })
+            }
+
+            pub fn trigger_name_required(&self) -> Result<String, HtmxError> {
+                self.trigger_name().ok_or_else(|| {
+                    self.required_header_error(RequestHeaders::HX_TRIGGER_NAME, HtmxError::MissingTriggerName)
+                })
+            }
+
+            /// `HtmxError::InvalidHeaderValue` when the header was sent but its value isn't
+            /// visible ASCII, so the accessor returned `None`. Otherwise `missing`.
+            fn required_header_error(&self, name: &str, missing: HtmxError) -> HtmxError {
+                let sent = self
+                    .read_inner()
+                    .raw_request_headers
+                    .iter()
+                    .any(|(raw_name, _)| raw_name == name);
+                if sent {
+                    HtmxError::InvalidHeaderValue(name.to_string())
+                } else {
+                    missing
+                }
+            }
+
+            /// Heuristic: htmx only sends `HX-Trigger-Name` when the triggering element has a `name`,
+            /// which in practice means a form or form control.
+            pub fn is_form_submission(&self) -> bool {
+                self.is_htmx && self.trigger_name().is_some()
+            }
+
+            /// Heuristic: both `HX-Trigger` and `HX-Trigger-Name` are set, suggesting a named form
+            /// element with an id submitted values.
+            pub fn has_form_values(&self) -> bool {
+                let inner = self.read_inner();
+                inner.get_string_header(RequestHeaders::HX_TRIGGER).is_some()
+                    && inner.get_string_header(RequestHeaders::HX_TRIGGER_NAME).is_some()
+            }
+
+            pub fn target_matches(&self, selector: &str) -> bool {
+                self.target().as_deref() == Some(selector)
+            }
+
+            pub fn target_matches_any(&self, selectors: &[&str]) -> bool {
+                match self.target() {
+                    Some(target) => selectors.contains(&target.as_str()),
+                    None => false,
+                }
+            }
+
+            pub fn trigger_matches(&self, id: &str) -> bool {
+                self.trigger().as_deref() == Some(id)
+            }
+
+            pub fn trigger_name_matches(&self, name: &str) -> bool {
+                self.trigger_name().as_deref() == Some(name)
+            }
+
+            pub fn request_info(&self) -> HtmxRequestInfo {
+                let inner = self.read_inner();
+                HtmxRequestInfo {
+                    is_htmx: self.is_htmx,
+                    boosted: self.boosted,
+                    history_restore_request: self.history_restore_request,
+                    current_url: inner.get_string_header(RequestHeaders::HX_CURRENT_URL),
+                    prompt: inner.get_string_header(RequestHeaders::HX_PROMPT),
+                    target: inner.get_string_header(RequestHeaders::HX_TARGET),
+                    trigger: inner.get_string_header(RequestHeaders::HX_TRIGGER),
+                    trigger_name: inner.get_string_header(RequestHeaders::HX_TRIGGER_NAME),
+                }
+            }
+
+            /// An htmx request that swaps content in place: not boosted and not a history restore.
+            /// See <https://htmx.org/reference/#request_headers>.
+            pub fn is_navigating(&self) -> bool {
+                self.is_htmx && !self.boosted && !self.history_restore_request
+            }
+
+            /// An htmx request that expects a partial response. History restores are excluded
+            /// because htmx wants the full page for them.
+            pub fn is_partial_request(&self) -> bool {
+                self.is_htmx && !self.history_restore_request
+            }
+
+            /// Set when htmx requests a page after missing its local history cache
+            /// (`HX-History-Restore-Request`).
+            pub fn is_ajax_history(&self) -> bool {
+                self.history_restore_request
+            }
+
+            /// Set when the request came from an element using `hx-boost` (`HX-Boosted`).
+            pub fn is_boosted_navigation(&self) -> bool {
+                self.boosted
+            }
+
+            /// True when the response should be a full page: a plain browser request, or an htmx
+            /// history restore, where htmx replaces the whole body with what it gets back.
+            pub fn is_first_load(&self) -> bool {
+                !self.is_htmx || self.history_restore_request
+            }
+
+            pub fn is_history_restore(&self) -> bool {
+                self.history_restore_request
+            }
+
+            pub fn require_htmx(&self) -> actix_web::Result<()> {
+                if !self.is_htmx {
+                    return Err(ErrorBadRequest("htmx required"));
+                }
+                Ok(())
+            }
+
+            pub fn require_boosted(&self) -> actix_web::Result<()> {
+                if !self.boosted {
+                    return Err(ErrorBadRequest("boosted request required"));
+                }
+                Ok(())
+            }
+
+            pub fn require_prompt(&self) -> actix_web::Result<()> {
+                if self.prompt().is_none() {
+                    return Err(ErrorBadRequest("htmx prompt required"));
+                }
+                Ok(())
+            }
+
+            pub fn require_target(&self) -> actix_web::Result<()> {
+                if self.target().is_none() {
+                    return Err(ErrorBadRequest("htmx target required"));
+                }
+                Ok(())
+            }
+
+            pub fn respond_partial_or_full<R: Responder>(
+                &self,
+                partial: impl FnOnce() -> R,
+                full: impl FnOnce() -> R,
+            ) -> R {
+                if self.is_htmx {
+                    partial()
+                } else {
+                    full()
+                }
+            }
+
+            pub async fn respond_partial_or_full_async<R, P, PF, F, FF>(&self, partial: P, full: F) -> R
+            where
+                R: Responder,
+                P: FnOnce() -> PF,
+                PF: Future<Output = R>,
+                F: FnOnce() -> FF,
+                FF: Future<Output = R>,
+            {
+                if self.is_htmx {
+                    partial().await
+                } else {
+                    full().await
+                }
+            }
+
+            /// Queues an event for the client. Events of the same [`TriggerType`] are written to their
+            /// header in the order they were first queued, so htmx fires them in that order. Queuing a
+            /// name again replaces its message but keeps its original position.
+            ///
+            /// ```
+            /// use actix_htmx::{Htmx, TriggerType};
+            ///
+            /// let htmx = Htmx::default();
+            /// htmx.trigger_event("a".to_string(), None, None);
+            /// htmx.trigger_event("b".to_string(), None, None);
+            /// htmx.trigger_event("a".to_string(), Some("again".to_string()), None);
+            ///
+            /// assert_eq!(htmx.get_trigger_names(TriggerType::Standard), ["a", "b"]);
+            /// assert_eq!(htmx.get_triggers(TriggerType::Standard)["a"].as_deref(), Some("again"));
+            /// ```
+            pub fn trigger_event(&self, name: String, message: Option<String>, trigger_type: Option<TriggerType>) {
+                let trigger_type = trigger_type.unwrap_or(TriggerType::Standard);
+                #[cfg(feature = "tracing")]
+                tracing::debug!(name = %name, trigger_type = %trigger_type, "htmx trigger queued");
+                self.write_inner()
+                    .triggers_mut(&trigger_type)
+                    .insert(name, message);
+            }
+
+            /// Queues the event only when `condition` is true. The other `*_if` setters work the same
+            /// way, doing nothing when `condition` is false.
+            pub fn trigger_event_if(
+                &self,
+                condition: bool,
+                name: String,
+                message: Option<String>,
+                trigger_type: Option<TriggerType>,
+            ) {
+                if condition {
+                    self.trigger_event(name, message, trigger_type);
+                }
+            }
+
+            pub fn trigger_typed_event(
+                &self,
+                name: &impl HtmxEventName,
+                message: Option<String>,
+                trigger_type: Option<TriggerType>,
+            ) {
+                self.trigger_event(name.event_name().to_string(), message, trigger_type);
+            }
+
+            pub fn trigger_event_once(&self, name: String, message: Option<String>, trigger_type: Option<TriggerType>) {
+                let trigger_type = trigger_type.unwrap_or(TriggerType::Standard);
+                self.write_inner()
+                    .triggers_mut(&trigger_type)
+                    .entry(name)
+                    .or_insert(message);
+            }
+
+            pub fn has_trigger(&self, name: &str, trigger_type: TriggerType) -> bool {
+                self.read_inner().triggers(&trigger_type).contains_key(name)
+            }
+
+            /// Replaces the message of an already queued event, keeping its position. Returns `false`,
+            /// queuing nothing, when no event with that name is queued.
+            pub fn replace_trigger_payload(
+                &self,
+                name: &str,
+                trigger_type: TriggerType,
+                message: Option<String>,
+            ) -> bool {
+                match self.write_inner().triggers_mut(&trigger_type).get_mut(name) {
+                    Some(existing) => {
+                        *existing = message;
+                        true
+                    }
+                    None => false,
+                }
+            }
+
+            pub fn get_trigger_names(&self, trigger_type: TriggerType) -> Vec<String> {
+                self.read_inner().triggers(&trigger_type).keys().cloned().collect()
+            }
+
+            pub fn get_trigger_count(&self, trigger_type: TriggerType) -> usize {
+                self.read_inner().triggers(&trigger_type).len()
+            }
+
+            pub fn trigger_event_batch<I>(&self, events: I)
+            where
+                I: IntoIterator,
+                I::Item: Into<HtmxEventSpec>,
+            {
+                let mut inner = self.write_inner();
+                events.into_iter().for_each(|event| {
+                    let event = event.into();
+                    inner
+                        .triggers_mut(&event.trigger_type)
+                        .insert(event.name, event.message);
+                });
+            }
+
+            pub fn remove_trigger(&self, name: &str, trigger_type: TriggerType) -> bool {
+                self.write_inner()
+                    .triggers_mut(&trigger_type)
+                    .shift_remove(name)
+                    .is_some()
+            }
+
+            pub fn clear_triggers(&self, trigger_type: TriggerType) {
+                self.write_inner().triggers_mut(&trigger_type).clear();
+            }
+
+            pub fn clear_all_triggers(&self) {
+                let mut inner = self.write_inner();
+                inner.standard_triggers.clear();
+                inner.after_settle_triggers.clear();
+                inner.after_swap_triggers.clear();
+            }
+
+            pub fn redirect(&self, path: impl Into<HxUrl>) {
+                self.write_inner()
+                    .response_headers
+                    .insert(ResponseHeaders::HX_REDIRECT.to_string(), path.into().to_string());
+            }
+
+            pub fn redirect_if(&self, condition: bool, path: impl Into<HxUrl>) {
+                if condition {
+                    self.redirect(path);
+                }
+            }
+
+            /// Sets `HX-Redirect` and queues a standard `HX-Trigger` event in one go. Both headers are
+            /// sent; htmx handles `HX-Trigger` before following the redirect, so the event fires on the
+            /// current page, not the one being navigated to.
+            pub fn redirect_with_trigger(
+                &self,
+                path: impl Into<HxUrl>,
+                event: impl Into<String>,
+                message: Option<String>,
+            ) {
+                let mut inner = self.write_inner();
+                inner
+                    .response_headers
+                    .insert(ResponseHeaders::HX_REDIRECT.to_string(), path.into().to_string());
+                inner
+                    .triggers_mut(&TriggerType::Standard)
+                    .insert(event.into(), message);
+            }
+
+            pub fn redirect_external(&self, url: impl Into<String>) -> Result<(), RedirectError> {
+                let url = url.into();
+                if !(url.starts_with("http://") || url.starts_with("https://") || url.starts_with("//")) {
+                    let inner = self.read_inner();
+                    log!(target: inner.log_target, inner.log_level, "Refusing to redirect to non-absolute url: {}", url);
+                    return Err(RedirectError::NotAbsoluteUrl(url));
+                }
+                self.redirect(url);
+                Ok(())
+            }
+
+            pub fn redirect_path(&self, path: impl Into<String>) -> Result<(), RedirectError> {
+                let path = path.into();
+                if !path.starts_with('/') || path.starts_with("//") {
+                    let inner = self.read_inner();
+                    log!(target: inner.log_target, inner.log_level, "Refusing to redirect to non-relative path: {}", path);
+                    return Err(RedirectError::NotRelativePath(path));
+                }
+                self.redirect(path);
+                Ok(())
+            }
+
+            pub fn conditional_redirect(
+                &self,
+                htmx_path: impl Into<HxUrl>,
+                full_path: impl Into<String>,
+            ) -> HttpResponse {
+                if self.is_htmx {
+                    self.redirect(htmx_path);
+                    HttpResponse::Ok().finish()
+                } else {
+                    HttpResponse::SeeOther()
+                        .insert_header((header::LOCATION, full_path.into()))
+                        .finish()
+                }
+            }
+
+            pub fn redirect_with_swap(&self, path: String) {
+                self.write_inner()
+                    .response_headers
+                    .insert(ResponseHeaders::HX_LOCATION.to_string(), path);
+            }
+
+            pub fn refresh(&self) {
+                self.write_inner()
+                    .response_headers
+                    .insert(ResponseHeaders::HX_REFRESH.to_string(), "true".to_string());
+            }
+
+            pub fn refresh_if(&self, condition: bool) {
+                if condition {
+                    self.refresh();
+                }
+            }
+
+            pub fn refresh_and_trigger(&self, event: impl Into<String>) {
+                let mut inner = self.write_inner();
+                inner
+                    .response_headers
+                    .insert(ResponseHeaders::HX_REFRESH.to_string(), "true".to_string());
+                inner
+                    .triggers_mut(&TriggerType::Standard)
+                    .insert(event.into(), None);
+            }
+
+            /// An empty `204 No Content` response, which htmx doesn't swap. Headers queued on this
+            /// value before or after the call are still written by the middleware.
+            pub fn send_no_content(&self) -> HttpResponse {
+                HttpResponse::NoContent().finish()
+            }
+
+            /// [`Self::send_no_content`] with a standard `HX-Trigger` event, for "saved, nothing to
+            /// swap" responses that still need to notify the page.
+            pub fn send_no_content_and_trigger(&self, event: impl Into<String>) -> HttpResponse {
+                self.write_inner()
+                    .triggers_mut(&TriggerType::Standard)
+                    .insert(event.into(), None);
+                self.send_no_content()
+            }
+
+            pub fn stop_polling(&self) {
+                self.write_inner()
+                    .response_headers
+                    .insert(ResponseHeaders::HX_STOP_POLLING.to_string(), "true".to_string());
+            }
+
+            pub fn stop_polling_if(&self, condition: bool) {
+                if condition {
+                    self.stop_polling();
+                }
+            }
+
+            pub fn redirect_and_stop_polling(&self, path: impl Into<HxUrl>) {
+                let mut inner = self.write_inner();
+                inner
+                    .response_headers
+                    .insert(ResponseHeaders::HX_STOP_POLLING.to_string(), "true".to_string());
+                inner
+                    .response_headers
+                    .insert(ResponseHeaders::HX_REDIRECT.to_string(), path.into().to_string());
+            }
+
+            /// Like [`Self::redirect_and_stop_polling`] but navigates with `HX-Location`, so `path` may be
+            /// either a plain path or the JSON form accepted by [`Self::redirect_with_swap`].
+            pub fn location_and_stop_polling(&self, path: impl Into<String>) {
+                let mut inner = self.write_inner();
+                inner
+                    .response_headers
+                    .insert(ResponseHeaders::HX_STOP_POLLING.to_string(), "true".to_string());
+                inner
+                    .response_headers
+                    .insert(ResponseHeaders::HX_LOCATION.to_string(), path.into());
+            }
+
+            /// Sets the `HX-Polling-Interval` header. This is not part of core htmx and needs a
+            /// client side extension that reads it.
+            pub fn set_polling_interval(&self, ms: u64) {
+                self.write_inner().response_headers.insert(
+                    ResponseHeaders::HX_POLLING_INTERVAL.to_string(),
+                    format!("{}ms", ms),
+                );
+            }
+
+            /// Stops polling once `is_done`, otherwise hints the next poll interval.
+            pub fn poll_until_done(&self, is_done: bool, interval_ms: u64) {
+                if is_done {
+                    self.stop_polling();
+                } else {
+                    self.set_polling_interval(interval_ms);
+                }
+            }
+
+            pub fn push_url(&self, path: impl Into<HxUrl>) {
+                self.write_inner()
+                    .response_headers
+                    .insert(ResponseHeaders::HX_PUSH_URL.to_string(), path.into().to_string());
+            }
+
+            pub fn push_url_if(&self, condition: bool, path: impl Into<HxUrl>) {
+                if condition {
+                    self.push_url(path);
+                }
+            }
+
+            pub fn replace_url(&self, path: impl Into<HxUrl>) {
+                self.write_inner()
+                    .response_headers
+                    .insert(ResponseHeaders::HX_REPLACE_URL.to_string(), path.into().to_string());
+            }
+
+            pub fn replace_url_if(&self, condition: bool, path: impl Into<HxUrl>) {
+                if condition {
+                    self.replace_url(path);
+                }
+            }
+
+            /// Sends `HX-Push-Url: false`, which stops htmx pushing a url even when the triggering
+            /// element asks for one. Not calling [`Self::push_url`] leaves the client's behaviour unchanged.
+            pub fn disable_push_url(&self) {
+                self.write_inner()
+                    .response_headers
+                    .insert(ResponseHeaders::HX_PUSH_URL.to_string(), "false".to_string());
+            }
+
+            /// Sends `HX-Replace-Url: false`, which stops htmx replacing the current url even when the
+            /// triggering element asks for it.
+            pub fn disable_replace_url(&self) {
+                self.write_inner()
+                    .response_headers
+                    .insert(ResponseHeaders::HX_REPLACE_URL.to_string(), "false".to_string());
+            }
+
+            /// Sends both `HX-Push-Url: false` and `HX-Replace-Url: false`, for responses that must not
+            /// change the browser url even when the triggering element has `hx-push-url` or
+            /// `hx-replace-url` set.
+            pub fn suppress_url_changes(&self) {
+                let mut inner = self.write_inner();
+                inner
+                    .response_headers
+                    .insert(ResponseHeaders::HX_PUSH_URL.to_string(), "false".to_string());
+                inner
+                    .response_headers
+                    .insert(ResponseHeaders::HX_REPLACE_URL.to_string(), "false".to_string());
+            }
+
+            pub fn is_url_change_suppressed(&self) -> bool {
+                let inner = self.read_inner();
+                inner.response_headers.get(ResponseHeaders::HX_PUSH_URL).map(String::as_str) == Some("false")
+                    && inner.response_headers.get(ResponseHeaders::HX_REPLACE_URL).map(String::as_str)
+                        == Some("false")
+            }
+
+            pub fn conditional_replace_url(&self, path: impl Into<HxUrl>) {
+                if self.is_htmx {
+                    self.replace_url(path);
+                }
+            }
+
+            pub fn reswap(&self, swap_type: SwapType) {
+                self.write_inner().response_headers.insert(
+                    ResponseHeaders::HX_RESWAP.to_string(),
+                    swap_type.to_string(),
+                );
+            }
+
+            pub fn reswap_if(&self, condition: bool, swap_type: SwapType) {
+                if condition {
+                    self.reswap(swap_type);
+                }
+            }
+
+            pub fn reswap_with_modifiers(&self, swap_type: SwapType, modifiers: &[SwapModifier]) {
+                let mut value = swap_type.to_string();
+                modifiers.iter().for_each(|modifier| {
+                    value.push(' ');
+                    value.push_str(&modifier.to_string());
+                });
+                self.write_inner()
+                    .response_headers
+                    .insert(ResponseHeaders::HX_RESWAP.to_string(), value);
+            }
+
+            pub fn reswap_with_scroll(&self, swap_type: SwapType, scroll_target: impl Into<CssSelector>) {
+                self.reswap_with_modifiers(swap_type, &[SwapModifier::Scroll(scroll_target.into())]);
+            }
+
+            pub fn reswap_with_show(&self, swap_type: SwapType, show_target: impl Into<CssSelector>) {
+                self.reswap_with_modifiers(swap_type, &[SwapModifier::Show(show_target.into())]);
+            }
+
+            pub fn reswap_builder(&self, swap_type: SwapType) -> HxReswap {
+                HxReswap::new(swap_type)
+            }
+
+            pub fn apply_reswap(&self, spec: HxReswap) {
+                self.write_inner()
+                    .response_headers
+                    .insert(ResponseHeaders::HX_RESWAP.to_string(), spec.build());
+            }
+
+            pub fn retarget(&self, selector: impl Into<CssSelector>) {
+                self.write_inner().response_headers.insert(
+                    ResponseHeaders::HX_RETARGET.to_string(),
+                    selector.into().to_string(),
+                );
+            }
+
+            pub fn retarget_if(&self, condition: bool, selector: impl Into<CssSelector>) {
+                if condition {
+                    self.retarget(selector);
+                }
+            }
+
+            pub fn reselect(&self, selector: impl Into<CssSelector>) {
+                self.write_inner().response_headers.insert(
+                    ResponseHeaders::HX_RESELECT.to_string(),
+                    selector.into().to_string(),
+                );
+            }
+
+            pub fn retarget_and_reswap(&self, selector: impl Into<CssSelector>, swap_type: SwapType) {
+                let mut inner = self.write_inner();
+                inner.response_headers.insert(
+                    ResponseHeaders::HX_RETARGET.to_string(),
+                    selector.into().to_string(),
+                );
+                inner
+                    .response_headers
+                    .insert(ResponseHeaders::HX_RESWAP.to_string(), swap_type.to_string());
+            }
+
+            pub fn swap_and_trigger(
+                &self,
+                target: impl Into<CssSelector>,
+                swap_type: SwapType,
+                event: impl Into<String>,
+                message: Option<String>,
+            ) {
+                let mut inner = self.write_inner();
+                inner.response_headers.insert(
+                    ResponseHeaders::HX_RETARGET.to_string(),
+                    target.into().to_string(),
+                );
+                inner
+                    .response_headers
+                    .insert(ResponseHeaders::HX_RESWAP.to_string(), swap_type.to_string());
+                inner
+                    .triggers_mut(&TriggerType::Standard)
+                    .insert(event.into(), message);
+            }
+
+            pub fn retarget_reswap_reselect(
+                &self,
+                selector: impl Into<CssSelector>,
+                swap_type: SwapType,
+                select: impl Into<CssSelector>,
+            ) {
+                let mut inner = self.write_inner();
+                inner.response_headers.insert(
+                    ResponseHeaders::HX_RETARGET.to_string(),
+                    selector.into().to_string(),
+                );
+                inner
+                    .response_headers
+                    .insert(ResponseHeaders::HX_RESWAP.to_string(), swap_type.to_string());
+                inner.response_headers.insert(
+                    ResponseHeaders::HX_RESELECT.to_string(),
+                    select.into().to_string(),
+                );
+            }
+
+            pub fn chain(&self) -> HtmxChain<'_, Self> {
+                HtmxChain::new(self)
+            }
+
+            pub fn vary_hx_request(&self) {
+                self.write_inner().vary_hx_request = true;
+            }
+
+            pub fn oob_swap(&self, spec: HxOobSpec) {
+                self.write_inner().oob_specs.push(spec);
+            }
+
+            pub fn get_oob_specs(&self) -> Vec<HxOobSpec> {
+                self.read_inner().oob_specs.clone()
+            }
+
+            pub fn validate_response(&self) -> Vec<HtmxResponseWarning> {
+                self.read_inner().validate()
+            }
+
+            pub fn diagnostics(&self) -> HtmxDiagnostics {
+                let inner = self.read_inner();
+                HtmxDiagnostics {
+                    standard_trigger_count: inner.standard_triggers.len(),
+                    after_settle_trigger_count: inner.after_settle_triggers.len(),
+                    after_swap_trigger_count: inner.after_swap_triggers.len(),
+                    response_header_count: inner.response_headers.len(),
+                    is_htmx: self.is_htmx,
+                }
+            }
+
+            pub fn has_response_header(&self, header: &str) -> bool {
+                self.read_inner()
+                    .response_headers
+                    .contains_key(&header.to_ascii_lowercase())
+            }
+
+            pub fn get_response_header(&self, header: &str) -> Option<String> {
+                self.read_inner()
+                    .response_headers
+                    .get(&header.to_ascii_lowercase())
+                    .cloned()
+            }
+
+            pub fn set_response_header(&self, name: impl Into<String>, value: impl Into<String>) {
+                self.write_inner()
+                    .response_headers
+                    .insert(name.into().to_ascii_lowercase(), value.into());
+            }
+
+            pub fn remove_response_header(&self, name: &str) -> bool {
+                self.write_inner()
+                    .response_headers
+                    .shift_remove(&name.to_ascii_lowercase())
+                    .is_some()
+            }
+
+            pub fn clear_response_headers(&self) {
+                self.write_inner().response_headers.clear();
+            }
+
+            pub fn has_redirect(&self) -> bool {
+                self.has_response_header(ResponseHeaders::HX_REDIRECT)
+            }
+
+            pub fn has_refresh(&self) -> bool {
+                self.has_response_header(ResponseHeaders::HX_REFRESH)
+            }
+
+            pub fn has_reswap(&self) -> bool {
+                self.has_response_header(ResponseHeaders::HX_RESWAP)
+            }
+
+            pub fn get_triggers(&self, trigger_type: TriggerType) -> IndexMap<String, Option<String>> {
+                self.read_inner().triggers(&trigger_type).clone()
+            }
+
+            pub fn get_all_triggers(&self) -> HashMap<TriggerType, IndexMap<String, Option<String>>> {
+                let inner = self.read_inner();
+                [TriggerType::Standard, TriggerType::AfterSettle, TriggerType::AfterSwap]
+                    .into_iter()
+                    .map(|trigger_type| {
+                        let triggers = inner.triggers(&trigger_type).clone();
+                        (trigger_type, triggers)
+                    })
+                    .collect()
+            }
+
+            pub fn total_trigger_count(&self) -> usize {
+                let inner = self.read_inner();
+                inner.standard_triggers.len() + inner.after_settle_triggers.len() + inner.after_swap_triggers.len()
+            }
+
+            pub fn into_response_state(self) -> HtmxResponseState {
+                let inner = self.read_inner();
+                HtmxResponseState {
+                    standard_triggers: inner.standard_triggers.clone(),
+                    after_settle_triggers: inner.after_settle_triggers.clone(),
+                    after_swap_triggers: inner.after_swap_triggers.clone(),
+                    response_headers: inner.response_headers.clone(),
+                }
+            }
+
+            /// The queued response headers and triggers as a JSON object, for sending over SSE or a
+            /// websocket where there are no response headers:
+            /// `{"headers": {..}, "triggers": {"standard": {..}, "afterSettle": {..}, "afterSwap": {..}}}`.
+            /// As in the `HX-Trigger` headers, messages that are JSON objects are embedded as objects.
+            #[cfg(feature = "serde")]
+            pub fn response_as_json(&self) -> serde_json::Value {
+                let inner = self.read_inner();
+                let triggers = |trigger_type: &TriggerType| {
+                    inner
+                        .triggers(trigger_type)
+                        .iter()
+                        .map(|(name, message)| {
+                            let message = match message {
+                                Some(message) if message.trim().starts_with('{') => serde_json::from_str(message)
+                                    .unwrap_or_else(|_| serde_json::Value::String(message.clone())),
+                                Some(message) => serde_json::Value::String(message.clone()),
+                                None => serde_json::Value::Null,
+                            };
+                            (name.clone(), message)
+                        })
+                        .collect::<serde_json::Map<_, _>>()
+                };
+
+                serde_json::json!({
+                    "headers": inner.response_headers,
+                    "triggers": {
+                        "standard": triggers(&TriggerType::Standard),
+                        "afterSettle": triggers(&TriggerType::AfterSettle),
+                        "afterSwap": triggers(&TriggerType::AfterSwap),
+                    },
+                })
+            }
+
+            /// Moves the queued triggers and response headers out, leaving nothing for the middleware
+            /// to write unless it's given back with [`Self::apply_response_state`].
+            pub fn take_response_state(&self) -> HtmxResponseState {
+                let mut inner = self.write_inner();
+                HtmxResponseState {
+                    standard_triggers: mem::take(&mut inner.standard_triggers),
+                    after_settle_triggers: mem::take(&mut inner.after_settle_triggers),
+                    after_swap_triggers: mem::take(&mut inner.after_swap_triggers),
+                    response_headers: mem::take(&mut inner.response_headers),
+                }
+            }
+
+            /// Merges a previously captured state into this one. Entries already queued with the same
+            /// name are overwritten.
+            pub fn apply_response_state(&self, state: HtmxResponseState) {
+                let mut inner = self.write_inner();
+                inner.standard_triggers.extend(state.standard_triggers);
+                inner.after_settle_triggers.extend(state.after_settle_triggers);
+                inner.after_swap_triggers.extend(state.after_swap_triggers);
+                inner.response_headers.extend(
+                    state
+                        .response_headers
+                        .into_iter()
+                        .map(|(key, value)| (key.to_ascii_lowercase(), value)),
+                );
+            }
+    };
+}
+
+pub(crate) use htmx_methods;
@@ -0,0 +1,20 @@
+use crate::TriggerType;
+
+/// Hooks called by the middleware, for counting htmx traffic. Every method defaults to
+/// doing nothing so implementations only need the ones they care about.
+pub trait HtmxMetrics: Send + Sync {
+    fn on_htmx_request(&self) {}
+
+    fn on_non_htmx_request(&self) {}
+
+    fn on_trigger(&self, _name: &str, _trigger_type: TriggerType) {}
+
+    fn on_redirect(&self) {}
+
+    fn on_refresh(&self) {}
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopMetrics;
+
+impl HtmxMetrics for NoopMetrics {}
@@ -1,17 +1,129 @@
-use crate::{headers::ResponseHeaders, Htmx, TriggerType};
+use crate::{
+    headers::{prefixed, RequestHeaders, ResponseHeaders},
+    htmx::HtmxInner,
+    Htmx, HtmxArc, HtmxConfig, HtmxMetrics, HtmxRouteConfig, TriggerType,
+};
 
-use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::error::InternalError;
+use actix_web::http::StatusCode;
+use actix_web::http::header::{self, HeaderMap, HeaderName, HeaderValue, InvalidHeaderName};
 use actix_web::{
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    Error, HttpMessage,
+    web, Error, HttpMessage,
 };
 use futures_util::future::LocalBoxFuture;
 use indexmap::IndexMap;
-use log::warn;
+use log::{debug, log, log_enabled, Level};
+use std::borrow::Cow;
+use std::fmt::Write;
 use std::future::{ready, Ready};
+use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct HtmxMiddleware;
 
+pub type HeaderErrorHandler = Box<dyn Fn(&str, &str) + Send + Sync>;
+
+pub type RequestFilter = Box<dyn Fn(&ServiceRequest) -> bool + Send + Sync>;
+
+pub struct HtmxMiddlewareConfig {
+    pub emit_vary_header: bool,
+    pub log_level: Level,
+    /// Answers every non-htmx request under this middleware with `reject_status`. To reject
+    /// them on some routes only, wrap those scopes in [`HtmxOnlyMiddleware`] instead.
+    pub reject_non_htmx: bool,
+    pub reject_status: StatusCode,
+    /// Only insert the extractor into the request, leaving responses untouched.
+    pub passthrough: bool,
+    pub metrics: Option<Arc<dyn HtmxMetrics>>,
+    pub log_target: &'static str,
+    /// Log each [`crate::HtmxResponseWarning`] found in the queued headers.
+    pub warn_on_conflicts: bool,
+    /// Called with the header name and value whenever a queued header can't be written.
+    pub on_header_error: Option<HeaderErrorHandler>,
+    /// Requests this returns `false` for get an empty non-htmx [`Htmx`] (or [`HtmxArc`]) and no
+    /// header processing.
+    pub filter: Option<RequestFilter>,
+    /// Replaces the `hx` in every request and response header name, e.g. `Some("hx2")` reads
+    /// `hx2-request` and writes `hx2-trigger`.
+    pub header_prefix: Option<&'static str>,
+}
+
+impl Default for HtmxMiddlewareConfig {
+    fn default() -> Self {
+        HtmxMiddlewareConfig {
+            emit_vary_header: false,
+            log_level: Level::Warn,
+            reject_non_htmx: false,
+            reject_status: StatusCode::BAD_REQUEST,
+            passthrough: false,
+            metrics: None,
+            log_target: "actix_htmx",
+            warn_on_conflicts: true,
+            on_header_error: None,
+            filter: None,
+            header_prefix: None,
+        }
+    }
+}
+
+impl HtmxMiddlewareConfig {
+    pub fn with_metrics(mut self, metrics: Arc<dyn HtmxMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn with_filter(mut self, predicate: impl Fn(&ServiceRequest) -> bool + Send + Sync + 'static) -> Self {
+        self.filter = Some(Box::new(predicate));
+        self
+    }
+}
+
+impl HtmxMiddleware {
+    pub fn with_config(config: HtmxMiddlewareConfig) -> ConfiguredHtmxMiddleware {
+        ConfiguredHtmxMiddleware {
+            config: Rc::new(config),
+            arc: false,
+        }
+    }
+
+    pub fn filter(predicate: impl Fn(&ServiceRequest) -> bool + Send + Sync + 'static) -> ConfiguredHtmxMiddleware {
+        HtmxMiddleware::with_config(HtmxMiddlewareConfig::default().with_filter(predicate))
+    }
+
+    /// Only processes requests whose path exactly matches one of `paths`.
+    pub fn filter_paths(paths: impl IntoIterator<Item = impl Into<String>>) -> ConfiguredHtmxMiddleware {
+        let paths = paths.into_iter().map(Into::into).collect::<Vec<String>>();
+        HtmxMiddleware::filter(move |req| paths.iter().any(|path| path == req.path()))
+    }
+
+    /// For htmx builds that use a different header prefix, e.g. `with_prefix("hx2")` for
+    /// `hx2-request`. Only the middleware and the [`Htmx`] it inserts know about the prefix:
+    /// use [`crate::HtmxGuard::with_prefix`] for guards, and note that [`crate::HtmxRequestExt`]
+    /// and an [`Htmx`] extracted without this middleware read the standard names.
+    pub fn with_prefix(prefix: &'static str) -> ConfiguredHtmxMiddleware {
+        HtmxMiddleware::with_config(HtmxMiddlewareConfig {
+            header_prefix: Some(prefix),
+            ..HtmxMiddlewareConfig::default()
+        })
+    }
+
+    /// Makes [`Htmx`] available to handlers without rejecting requests or writing any
+    /// response headers. Request headers are still parsed, so `is_htmx` reflects the request.
+    /// Useful for checking a handler's response body without the htmx headers it queues.
+    pub fn passthrough() -> ConfiguredHtmxMiddleware {
+        HtmxMiddleware::with_config(HtmxMiddlewareConfig {
+            passthrough: true,
+            ..HtmxMiddlewareConfig::default()
+        })
+    }
+}
+
+pub struct ConfiguredHtmxMiddleware {
+    config: Rc<HtmxMiddlewareConfig>,
+    arc: bool,
+}
+
 impl<S, B> Transform<S, ServiceRequest> for HtmxMiddleware
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
@@ -25,7 +137,103 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ready(Ok(InnerHtmxMiddleware { service }))
+        HtmxMiddleware::with_config(HtmxMiddlewareConfig::default()).new_transform(service)
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ConfiguredHtmxMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = InnerHtmxMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(InnerHtmxMiddleware {
+            service,
+            config: self.config.clone(),
+            arc: self.arc,
+        }))
+    }
+}
+
+/// Like [`HtmxMiddleware`], but inserts an [`HtmxArc`] that can be moved into spawned tasks.
+pub struct HtmxArcMiddleware;
+
+impl HtmxArcMiddleware {
+    pub fn with_config(config: HtmxMiddlewareConfig) -> ConfiguredHtmxMiddleware {
+        ConfiguredHtmxMiddleware {
+            config: Rc::new(config),
+            arc: true,
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for HtmxArcMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = InnerHtmxMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        HtmxArcMiddleware::with_config(HtmxMiddlewareConfig::default()).new_transform(service)
+    }
+}
+
+/// Rejects non-htmx requests with the given status without calling the wrapped service,
+/// e.g. `.wrap(HtmxOnlyMiddleware::new(StatusCode::NOT_FOUND))` on a scope of htmx-only routes.
+/// Otherwise behaves like [`HtmxMiddleware`].
+pub struct HtmxOnlyMiddleware {
+    config: Rc<HtmxMiddlewareConfig>,
+}
+
+impl HtmxOnlyMiddleware {
+    pub fn new(status: StatusCode) -> HtmxOnlyMiddleware {
+        HtmxOnlyMiddleware {
+            config: Rc::new(HtmxMiddlewareConfig {
+                reject_non_htmx: true,
+                reject_status: status,
+                ..HtmxMiddlewareConfig::default()
+            }),
+        }
+    }
+}
+
+impl Default for HtmxOnlyMiddleware {
+    fn default() -> Self {
+        HtmxOnlyMiddleware::new(StatusCode::BAD_REQUEST)
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for HtmxOnlyMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = InnerHtmxMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(InnerHtmxMiddleware {
+            service,
+            config: self.config.clone(),
+            arc: false,
+        }))
     }
 }
 
@@ -33,6 +241,8 @@ where
 #[non_exhaustive]
 pub struct InnerHtmxMiddleware<S> {
     service: S,
+    config: Rc<HtmxMiddlewareConfig>,
+    arc: bool,
 }
 
 impl<S, B> Service<ServiceRequest> for InnerHtmxMiddleware<S>
@@ -48,98 +258,1120 @@ where
     forward_ready!(service);
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        let htmx = Htmx::new(&req);
+        if let Some(filter) = &self.config.filter {
+            if !filter(&req) {
+                if self.arc {
+                    req.extensions_mut().insert(HtmxArc::default());
+                } else {
+                    req.extensions_mut().insert(Htmx::default());
+                }
+                return Box::pin(self.service.call(req));
+            }
+        }
+
+        let app_config = req.app_data::<web::Data<HtmxConfig>>().cloned();
+        let log_level = app_config
+            .as_ref()
+            .and_then(|app_config| app_config.log_level)
+            .unwrap_or(self.config.log_level);
+
+        let prefix = self.config.header_prefix;
+        let is_htmx = if self.arc {
+            let htmx = HtmxArc::with_header_prefix(&req, prefix);
+            htmx.log_to(self.config.log_target, log_level);
+            let is_htmx = htmx.is_htmx;
+            req.extensions_mut().insert(htmx);
+            is_htmx
+        } else {
+            let htmx = Htmx::with_header_prefix(&req, prefix);
+            htmx.log_to(self.config.log_target, log_level);
+            let is_htmx = htmx.is_htmx;
+            req.extensions_mut().insert(htmx);
+            is_htmx
+        };
+
+        if self.config.passthrough {
+            return Box::pin(self.service.call(req));
+        }
+
+        if let Some(metrics) = &self.config.metrics {
+            if is_htmx {
+                metrics.on_htmx_request();
+            } else {
+                metrics.on_non_htmx_request();
+            }
+        }
+
+        if !is_htmx && self.config.reject_non_htmx {
+            let err = InternalError::new("htmx request required", self.config.reject_status);
+            return Box::pin(ready(Err(err.into())));
+        }
 
-        req.extensions_mut().insert(htmx);
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(
+            "htmx_middleware_call",
+            is_htmx,
+            trigger_count_standard = tracing::field::Empty,
+            trigger_count_after_settle = tracing::field::Empty,
+            trigger_count_after_swap = tracing::field::Empty,
+        );
 
+        if log_enabled!(target: self.config.log_target, Level::Debug) {
+            let info = if self.arc {
+                req.extensions().get::<HtmxArc>().map(HtmxArc::request_info)
+            } else {
+                req.extensions().get::<Htmx>().map(Htmx::request_info)
+            };
+            if let Some(info) = info {
+                debug!(
+                    target: self.config.log_target,
+                    is_htmx = is_htmx,
+                    boosted = info.boosted,
+                    history_restore_request = info.history_restore_request,
+                    target:? = info.target,
+                    trigger:? = info.trigger,
+                    current_url:? = info.current_url;
+                    "htmx request"
+                );
+            }
+        }
+
+        let config = self.config.clone();
         let fut = self.service.call(req);
 
-        Box::pin(async move {
+        let fut = async move {
             let res: ServiceResponse<B> = fut.await?;
 
             let (req, mut res) = res.into_parts();
 
-            let trigger_json = |trigger_map: IndexMap<String, Option<String>>| -> String {
-                let mut triggers = String::new();
-                triggers.push('{');
-                trigger_map.iter().for_each(|(key, value)| {
-                    if let Some(value) = value {
-                        if value.trim().starts_with('{') {
-                            triggers.push_str(&format!("\"{}\": {},", key, value));
-                        } else {
-                            triggers.push_str(&format!("\"{}\": \"{}\",", key, value));
+            let mut vary_hx_request = config.emit_vary_header;
+            let mut default_reswap = None;
+
+            if let Some(app_config) = &app_config {
+                vary_hx_request |= app_config.auto_vary_header;
+                default_reswap = app_config.default_reswap.clone();
+            }
+
+            if let Some(route_config) = req.app_data::<web::Data<HtmxRouteConfig>>() {
+                if let Some(auto_vary_header) = route_config.auto_vary_header {
+                    vary_hx_request = config.emit_vary_header || auto_vary_header;
+                }
+                if route_config.default_reswap.is_some() {
+                    default_reswap = route_config.default_reswap.clone();
+                }
+            }
+
+            if is_htmx {
+                if let Some(swap_type) = &default_reswap {
+                    let name = prefixed_header_name(ResponseHeaders::HX_RESWAP, config.header_prefix);
+                    if let (Ok(name), Ok(value)) = (name, HeaderValue::from_str(swap_type.as_ref())) {
+                        if !res.headers().contains_key(&name) {
+                            res.headers_mut().insert(name, value);
                         }
                     }
-                    else {
-                        triggers.push_str(&format!("\"{}\": null,", key));
-                    }
+                }
+            }
+
+            if let Some(htmx_response) = req.extensions().get::<Htmx>() {
+                let inner = htmx_response.read_inner();
+                if let Some(metrics) = &config.metrics {
+                    record_metrics(&inner, metrics.as_ref());
+                }
+                vary_hx_request |= write_htmx_headers(&inner, res.headers_mut(), &config, log_level);
+            } else if let Some(htmx_response) = req.extensions().get::<HtmxArc>() {
+                let inner = htmx_response.lock_inner();
+                if let Some(metrics) = &config.metrics {
+                    record_metrics(&inner, metrics.as_ref());
+                }
+                vary_hx_request |= write_htmx_headers(&inner, res.headers_mut(), &config, log_level);
+            }
+
+            let hx_request = prefixed(RequestHeaders::HX_REQUEST, config.header_prefix);
+            let already_varies = res
+                .headers()
+                .get_all(header::VARY)
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .any(|value| {
+                    let value = value.trim();
+                    value == "*" || value.eq_ignore_ascii_case(&hx_request)
                 });
-                triggers.pop();
-                triggers.push('}');
-                triggers
+
+            if vary_hx_request && !already_varies {
+                if let Ok(value) = HeaderValue::from_str(&hx_request) {
+                    res.headers_mut().append(header::VARY, value);
+                }
+            }
+
+            Ok(ServiceResponse::new(req, res))
+        };
+
+        #[cfg(feature = "tracing")]
+        let fut = tracing::Instrument::instrument(fut, span);
+
+        Box::pin(fut)
+    }
+}
+
+#[doc(hidden)]
+pub fn trigger_json(trigger_map: &IndexMap<String, Option<String>>) -> String {
+    if trigger_map.is_empty() {
+        return String::new();
+    }
+
+    let capacity = trigger_map
+        .iter()
+        .map(|(key, value)| key.len() + value.as_ref().map_or(4, String::len) + 6)
+        .sum::<usize>()
+        + 2;
+    let mut triggers = String::with_capacity(capacity);
+    triggers.push('{');
+    trigger_map.iter().enumerate().for_each(|(i, (key, value))| {
+        if i > 0 {
+            triggers.push(',');
+        }
+        push_json_string(&mut triggers, key);
+        triggers.push_str(": ");
+        match value {
+            Some(value) if value.trim().starts_with('{') => triggers.push_str(value),
+            Some(value) => push_json_string(&mut triggers, value),
+            None => triggers.push_str("null"),
+        }
+    });
+    triggers.push('}');
+    triggers
+}
+
+/// Writes `value` as a quoted JSON string, escaping quotes, backslashes and control characters.
+fn push_json_string(out: &mut String, value: &str) {
+    out.push('"');
+    value.chars().for_each(|c| match c {
+        '"' => out.push_str("\\\""),
+        '\\' => out.push_str("\\\\"),
+        '\n' => out.push_str("\\n"),
+        '\r' => out.push_str("\\r"),
+        '\t' => out.push_str("\\t"),
+        // Writing to a String can't fail.
+        c if (c as u32) < 0x20 => {
+            let _ = write!(out, "\\u{:04x}", c as u32);
+        }
+        c => out.push(c),
+    });
+    out.push('"');
+}
+
+#[doc(hidden)]
+pub fn simple_header(trigger_map: &IndexMap<String, Option<String>>) -> String {
+    if let (1, Some(key)) = (trigger_map.len(), trigger_map.keys().next()) {
+        return key.clone();
+    }
+
+    let capacity = trigger_map.keys().map(|key| key.len() + 1).sum();
+    let mut triggers = String::with_capacity(capacity);
+    trigger_map.keys().enumerate().for_each(|(i, key)| {
+        if i > 0 {
+            triggers.push(',');
+        }
+        triggers.push_str(key);
+    });
+    triggers
+}
+
+fn prefixed_header_name(name: &'static str, prefix: Option<&str>) -> Result<HeaderName, InvalidHeaderName> {
+    match prefixed(name, prefix) {
+        Cow::Borrowed(name) => Ok(HeaderName::from_static(name)),
+        Cow::Owned(name) => HeaderName::try_from(name),
+    }
+}
+
+fn record_metrics(inner: &HtmxInner, metrics: &dyn HtmxMetrics) {
+    [TriggerType::Standard, TriggerType::AfterSettle, TriggerType::AfterSwap]
+        .iter()
+        .for_each(|trigger_type| {
+            inner
+                .triggers(trigger_type)
+                .keys()
+                .for_each(|name| metrics.on_trigger(name, trigger_type.clone()));
+        });
+
+    if inner.response_headers.contains_key(ResponseHeaders::HX_REDIRECT) {
+        metrics.on_redirect();
+    }
+    if inner.response_headers.contains_key(ResponseHeaders::HX_REFRESH) {
+        metrics.on_refresh();
+    }
+}
+
+fn write_htmx_headers(
+    inner: &HtmxInner,
+    headers: &mut HeaderMap,
+    config: &HtmxMiddlewareConfig,
+    log_level: Level,
+) -> bool {
+    if config.warn_on_conflicts {
+        inner.validate().iter().for_each(|warning| {
+            log!(target: config.log_target, log_level, "Conflicting htmx response headers: {}", warning)
+        });
+    }
+
+    let header_error = |name: &str, value: &str| {
+        if let Some(on_header_error) = &config.on_header_error {
+            on_header_error(name, value);
+        }
+    };
+
+    #[cfg(feature = "tracing")]
+    {
+        let span = tracing::Span::current();
+        span.record("trigger_count_standard", inner.triggers(&TriggerType::Standard).len());
+        span.record("trigger_count_after_settle", inner.triggers(&TriggerType::AfterSettle).len());
+        span.record("trigger_count_after_swap", inner.triggers(&TriggerType::AfterSwap).len());
+    }
+
+    [TriggerType::Standard, TriggerType::AfterSettle, TriggerType::AfterSwap]
+        .iter()
+        .for_each(|trigger_type| {
+            let trigger_map = inner.triggers(trigger_type);
+            if trigger_map.is_empty() {
+                return;
+            }
+
+            let triggers = if inner.is_simple_trigger(trigger_type) {
+                simple_header(trigger_map)
+            }
+            else {
+                trigger_json(trigger_map)
             };
 
-            let simple_header = |trigger_map: IndexMap<String, Option<String>>| -> String {
-                let mut triggers = trigger_map.iter().map(|(key, _)| key.to_string() + ",").collect::<String>();
-                triggers.pop();
-                triggers
+            let header_name = match prefixed_header_name(trigger_type.header_name(), config.header_prefix) {
+                Ok(header_name) => header_name,
+                Err(_) => {
+                    let header_name = prefixed(trigger_type.header_name(), config.header_prefix);
+                    log!(target: config.log_target, log_level, "Failed to parse header name: {}", header_name);
+                    header_error(&header_name, &triggers);
+                    return;
+                }
             };
 
-            let mut process_trigger_header =
-                |header_name: HeaderName, trigger_map: IndexMap<String, Option<String>>, simple: bool| {
-                    if trigger_map.is_empty() {
-                        return;
-                    }
+            if let Ok(value) = HeaderValue::from_str(&triggers) {
+                headers.insert(header_name, value);
+            } else {
+                log!(
+                    target: config.log_target,
+                    log_level,
+                    "Failed to parse {} header value: {}",
+                    header_name,
+                    triggers
+                );
+                header_error(header_name.as_str(), &triggers);
+            }
+        });
 
-                    let triggers = if simple {
-                        simple_header(trigger_map)
-                    }
-                    else {
-                        trigger_json(trigger_map)
-                    };
-
-                    if let Ok(value) = HeaderValue::from_str(&triggers) {
-                        res.headers_mut().insert(header_name, value);
-                    } else {
-                        warn!("Failed to parse {} header value: {}", header_name, triggers)
-                    }
-                };
+    inner
+        .response_headers
+        .iter()
+        .for_each(|(key, value)| match prefixed(key, config.header_prefix).parse::<HeaderName>() {
+            Ok(key) => {
+                if let Ok(value) = HeaderValue::from_str(value) {
+                    headers.insert(key, value);
+                } else {
+                    log!(target: config.log_target, log_level, "Failed to parse {} header value: {}", key, value);
+                    header_error(key.as_str(), value);
+                }
+            }
+            _ => {
+                log!(target: config.log_target, log_level, "Failed to parse header name: {}", key);
+                header_error(key, value);
+            }
+        });
 
-            if let Some(htmx_response) = req.extensions().get::<Htmx>() {
-                process_trigger_header(
-                    HeaderName::from_static(ResponseHeaders::HX_TRIGGER),
-                    htmx_response.get_triggers(TriggerType::Standard),
-                    htmx_response.is_simple_trigger(TriggerType::Standard)
-                );
-                process_trigger_header(
-                    HeaderName::from_static(ResponseHeaders::HX_TRIGGER_AFTER_SETTLE),
-                    htmx_response.get_triggers(TriggerType::AfterSettle),
-                    htmx_response.is_simple_trigger(TriggerType::AfterSettle)
-                );
-                process_trigger_header(
-                    HeaderName::from_static(ResponseHeaders::HX_TRIGGER_AFTER_SWAP),
-                    htmx_response.get_triggers(TriggerType::AfterSwap),
-                    htmx_response.is_simple_trigger(TriggerType::AfterSwap)
-                );
+    if log_enabled!(target: config.log_target, Level::Debug) {
+        let response_header_names = inner
+            .response_headers
+            .keys()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(",");
+        debug!(
+            target: config.log_target,
+            standard_trigger_count = inner.triggers(&TriggerType::Standard).len(),
+            after_settle_trigger_count = inner.triggers(&TriggerType::AfterSettle).len(),
+            after_swap_trigger_count = inner.triggers(&TriggerType::AfterSwap).len(),
+            response_header_names = response_header_names.as_str();
+            "htmx response"
+        );
+    }
+
+    inner.vary_hx_request
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::HeaderMap;
+    use actix_web::test::{self, TestRequest};
+    use actix_web::{App, HttpResponse};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn htmx_request() -> TestRequest {
+        TestRequest::get().uri("/").insert_header(("hx-request", "true"))
+    }
+
+    fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+        headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    fn error_status(err: Error) -> StatusCode {
+        err.as_response_error().status_code()
+    }
+
+    #[actix_web::test]
+    async fn arc_trigger_from_spawned_task_reaches_response() {
+        let app = test::init_service(App::new().wrap(HtmxArcMiddleware).route(
+            "/",
+            web::get().to(|htmx: HtmxArc| async move {
+                let task_htmx = htmx.clone();
+                tokio::spawn(async move {
+                    task_htmx.trigger_event("saved".to_string(), None, None);
+                })
+                .await
+                .unwrap();
+                HttpResponse::Ok().finish()
+            }),
+        ))
+        .await;
+
+        let res = test::call_service(&app, htmx_request().to_request()).await;
+        assert_eq!(header(res.headers(), "hx-trigger"), Some("saved"));
+    }
+
+    #[actix_web::test]
+    async fn arc_middleware_applies_config() {
+        let config = HtmxMiddlewareConfig {
+            reject_non_htmx: true,
+            header_prefix: Some("hx2"),
+            ..HtmxMiddlewareConfig::default()
+        };
+        let app = test::init_service(App::new().wrap(HtmxArcMiddleware::with_config(config)).route(
+            "/",
+            web::get().to(|htmx: HtmxArc| async move {
+                htmx.refresh();
+                HttpResponse::Ok().body(htmx.is_htmx.to_string())
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::get().uri("/").insert_header(("hx2-request", "true"));
+        let res = test::call_service(&app, req.to_request()).await;
+        assert_eq!(header(res.headers(), "hx2-refresh"), Some("true"));
+        assert_eq!(test::read_body(res).await, "true");
+
+        let err = test::try_call_service(&app, htmx_request().to_request()).await.unwrap_err();
+        assert_eq!(error_status(err), StatusCode::BAD_REQUEST);
+    }
+
+    async fn ok() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_web::test]
+    async fn emit_vary_header_adds_vary_to_every_response() {
+        let config = HtmxMiddlewareConfig {
+            emit_vary_header: true,
+            ..HtmxMiddlewareConfig::default()
+        };
+        let app = test::init_service(
+            App::new()
+                .wrap(HtmxMiddleware::with_config(config))
+                .route("/", web::get().to(ok)),
+        )
+        .await;
+
+        let res = test::call_service(&app, htmx_request().to_request()).await;
+        assert_eq!(header(res.headers(), "vary"), Some("hx-request"));
+        let res = test::call_service(&app, TestRequest::get().uri("/").to_request()).await;
+        assert_eq!(header(res.headers(), "vary"), Some("hx-request"));
+
+        let app = test::init_service(App::new().wrap(HtmxMiddleware).route("/", web::get().to(ok))).await;
+        let res = test::call_service(&app, htmx_request().to_request()).await;
+        assert!(res.headers().get("vary").is_none());
+    }
+
+    #[actix_web::test]
+    async fn reject_non_htmx_returns_bad_request() {
+        let config = HtmxMiddlewareConfig {
+            reject_non_htmx: true,
+            ..HtmxMiddlewareConfig::default()
+        };
+        let app = test::init_service(
+            App::new()
+                .wrap(HtmxMiddleware::with_config(config))
+                .route("/", web::get().to(ok)),
+        )
+        .await;
 
-                let response_headers = htmx_response.get_response_headers();
-                response_headers
-                    .iter()
-                    .for_each(|(key, value)| match key.parse() {
-                        Ok(key) => {
-                            if let Ok(value) = HeaderValue::from_str(value) {
-                                res.headers_mut().insert(key, value);
-                            } else {
-                                warn!("Failed to parse {} header value: {}", key, value)
+        let err = test::try_call_service(&app, TestRequest::get().uri("/").to_request())
+            .await
+            .unwrap_err();
+        assert_eq!(error_status(err), StatusCode::BAD_REQUEST);
+        let res = test::call_service(&app, htmx_request().to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    fn vary_values(headers: &HeaderMap) -> Vec<&str> {
+        headers
+            .get_all(header::VARY)
+            .filter_map(|value| value.to_str().ok())
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn vary_is_appended_to_existing_values() {
+        let config = HtmxMiddlewareConfig {
+            emit_vary_header: true,
+            ..HtmxMiddlewareConfig::default()
+        };
+        let app = test::init_service(
+            App::new()
+                .wrap(HtmxMiddleware::with_config(config))
+                .route(
+                    "/",
+                    web::get().to(|| async {
+                        HttpResponse::Ok().insert_header((header::VARY, "Accept-Encoding")).finish()
+                    }),
+                )
+                .route(
+                    "/varies",
+                    web::get().to(|| async {
+                        HttpResponse::Ok().insert_header((header::VARY, "Accept, HX-Request")).finish()
+                    }),
+                ),
+        )
+        .await;
+
+        let res = test::call_service(&app, htmx_request().to_request()).await;
+        assert_eq!(vary_values(res.headers()), ["Accept-Encoding", "hx-request"]);
+
+        let req = TestRequest::get().uri("/varies").insert_header(("hx-request", "true"));
+        let res = test::call_service(&app, req.to_request()).await;
+        assert_eq!(vary_values(res.headers()), ["Accept, HX-Request"]);
+    }
+
+    #[actix_web::test]
+    async fn vary_hx_request_from_handler() {
+        let app = test::init_service(App::new().wrap(HtmxMiddleware).route(
+            "/",
+            web::get().to(|htmx: Htmx| async move {
+                htmx.vary_hx_request();
+                HttpResponse::Ok().insert_header((header::VARY, "Cookie")).finish()
+            }),
+        ))
+        .await;
+
+        let res = test::call_service(&app, htmx_request().to_request()).await;
+        assert_eq!(vary_values(res.headers()), ["Cookie", "hx-request"]);
+    }
+
+    #[actix_web::test]
+    async fn app_config_defaults_apply_without_handler_calls() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(HtmxConfig {
+                    default_reswap: Some(crate::SwapType::OuterHtml),
+                    auto_vary_header: true,
+                    ..HtmxConfig::default()
+                }))
+                .wrap(HtmxMiddleware)
+                .route("/", web::get().to(ok))
+                .route(
+                    "/reswap",
+                    web::get().to(|htmx: Htmx| async move {
+                        htmx.reswap(crate::SwapType::BeforeEnd);
+                        HttpResponse::Ok().finish()
+                    }),
+                ),
+        )
+        .await;
+
+        let res = test::call_service(&app, htmx_request().to_request()).await;
+        assert_eq!(header(res.headers(), "hx-reswap"), Some("outerHTML"));
+        assert_eq!(header(res.headers(), "vary"), Some("hx-request"));
+
+        let res = test::call_service(&app, TestRequest::get().uri("/").to_request()).await;
+        assert!(res.headers().get("hx-reswap").is_none());
+
+        let req = TestRequest::get().uri("/reswap").insert_header(("hx-request", "true"));
+        let res = test::call_service(&app, req.to_request()).await;
+        assert_eq!(header(res.headers(), "hx-reswap"), Some("beforeend"));
+    }
+
+    #[actix_web::test]
+    async fn passthrough_runs_handlers_without_writing_headers() {
+        let app = test::init_service(App::new().wrap(HtmxMiddleware::passthrough()).route(
+            "/",
+            web::get().to(|htmx: Htmx| async move {
+                htmx.refresh();
+                htmx.vary_hx_request();
+                if htmx.is_htmx {
+                    HttpResponse::Ok().body("partial")
+                } else {
+                    HttpResponse::Ok().body("full")
+                }
+            }),
+        ))
+        .await;
+
+        let res = test::call_service(&app, TestRequest::get().uri("/").to_request()).await;
+        assert!(res.headers().get("hx-refresh").is_none());
+        assert!(res.headers().get("vary").is_none());
+        assert_eq!(test::read_body(res).await, "full");
+
+        let res = test::call_service(&app, htmx_request().to_request()).await;
+        assert!(res.headers().get("hx-refresh").is_none());
+        assert_eq!(test::read_body(res).await, "partial");
+    }
+
+    #[actix_web::test]
+    async fn htmx_only_middleware_rejects_with_configured_status() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler_calls = calls.clone();
+        let app = test::init_service(
+            App::new().service(
+                web::scope("/partials")
+                    .wrap(HtmxOnlyMiddleware::new(StatusCode::NOT_FOUND))
+                    .route(
+                        "/list",
+                        web::get().to(move |htmx: Htmx| {
+                            handler_calls.fetch_add(1, Ordering::SeqCst);
+                            async move {
+                                htmx.trigger_event("loaded".to_string(), None, None);
+                                HttpResponse::Ok().finish()
                             }
-                        }
-                        _ => {
-                            warn!("Failed to parse header name: {}", key)
-                        }
-                    });
+                        }),
+                    ),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/partials/list").to_request();
+        let err = test::try_call_service(&app, req).await.unwrap_err();
+        assert_eq!(error_status(err), StatusCode::NOT_FOUND);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        let req = TestRequest::get().uri("/partials/list").insert_header(("hx-request", "true"));
+        let res = test::call_service(&app, req.to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(header(res.headers(), "hx-trigger"), Some("loaded"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let default = HtmxOnlyMiddleware::default();
+        assert_eq!(default.config.reject_status, StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn handler_beats_route_config_beats_app_config() {
+        use crate::SwapType;
+
+        let reswap_to_inner_html = |htmx: Htmx| async move {
+            htmx.reswap(SwapType::InnerHtml);
+            HttpResponse::Ok().finish()
+        };
+        let reswap_on_response = || async { HttpResponse::Ok().insert_header(("HX-Reswap", "afterbegin")).finish() };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(HtmxConfig {
+                    default_reswap: Some(SwapType::OuterHtml),
+                    auto_vary_header: true,
+                    ..HtmxConfig::default()
+                }))
+                .wrap(HtmxMiddleware)
+                .service(
+                    web::scope("/scoped")
+                        .app_data(web::Data::new(
+                            HtmxRouteConfig::new()
+                                .default_reswap(SwapType::BeforeEnd)
+                                .auto_vary_header(false),
+                        ))
+                        .route("/default", web::get().to(ok))
+                        .route("/handler", web::get().to(reswap_to_inner_html))
+                        .route("/response", web::get().to(reswap_on_response)),
+                )
+                .route("/default", web::get().to(ok))
+                .route("/handler", web::get().to(reswap_to_inner_html))
+                .route("/response", web::get().to(reswap_on_response)),
+        )
+        .await;
+
+        let cases = [
+            ("/default", "outerHTML", Some("hx-request")),
+            ("/handler", "innerHTML", Some("hx-request")),
+            ("/response", "afterbegin", Some("hx-request")),
+            ("/scoped/default", "beforeend", None),
+            ("/scoped/handler", "innerHTML", None),
+            ("/scoped/response", "afterbegin", None),
+        ];
+        for (uri, reswap, vary) in cases {
+            let req = TestRequest::get().uri(uri).insert_header(("hx-request", "true"));
+            let res = test::call_service(&app, req.to_request()).await;
+            assert_eq!(header(res.headers(), "hx-reswap"), Some(reswap), "{uri}");
+            assert_eq!(header(res.headers(), "vary"), vary, "{uri}");
+        }
+
+        let app = test::init_service(App::new().wrap(HtmxMiddleware).route("/", web::get().to(ok))).await;
+        let res = test::call_service(&app, htmx_request().to_request()).await;
+        assert!(res.headers().get("hx-reswap").is_none());
+    }
+
+    #[actix_web::test]
+    async fn filtered_paths_get_a_non_htmx_extractor_and_no_headers() {
+        let refresh = |htmx: Htmx| async move {
+            htmx.refresh();
+            HttpResponse::Ok().body(htmx.is_htmx.to_string())
+        };
+        let app = test::init_service(
+            App::new()
+                .wrap(HtmxMiddleware::filter_paths(["/partials"]))
+                .route("/partials", web::get().to(refresh))
+                .route("/static", web::get().to(refresh)),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/partials").insert_header(("hx-request", "true"));
+        let res = test::call_service(&app, req.to_request()).await;
+        assert_eq!(header(res.headers(), "hx-refresh"), Some("true"));
+        assert_eq!(test::read_body(res).await, "true");
+
+        let req = TestRequest::get().uri("/static").insert_header(("hx-request", "true"));
+        let res = test::call_service(&app, req.to_request()).await;
+        assert!(res.headers().get("hx-refresh").is_none());
+        assert_eq!(test::read_body(res).await, "false");
+    }
+
+    #[actix_web::test]
+    async fn filter_predicate_skips_header_processing() {
+        let config = HtmxMiddlewareConfig {
+            reject_non_htmx: true,
+            ..HtmxMiddlewareConfig::default()
+        }
+        .with_filter(|req| !req.path().starts_with("/assets"));
+        let app = test::init_service(
+            App::new()
+                .wrap(HtmxMiddleware::with_config(config))
+                .route("/assets/app.css", web::get().to(ok)),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/assets/app.css").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn filter_paths_accepts_owned_paths() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        let paths = vec!["/partials".to_string()];
+        let config = HtmxMiddlewareConfig::default().with_filter(|req| req.path() != "/health");
+        assert_send_sync(config.filter.as_ref().unwrap());
+
+        let app = test::init_service(
+            App::new()
+                .wrap(HtmxMiddleware::filter_paths(paths))
+                .route("/partials", web::get().to(|htmx: Htmx| async move {
+                    HttpResponse::Ok().body(htmx.is_htmx.to_string())
+                })),
+        )
+        .await;
+
+        let res = test::call_service(&app, htmx_request().uri("/partials").to_request()).await;
+        assert_eq!(test::read_body(res).await, "true");
+    }
+
+    #[actix_web::test]
+    async fn header_prefix_reads_and_writes_prefixed_names() {
+        let app = test::init_service(App::new().wrap(HtmxMiddleware::with_prefix("hx2")).route(
+            "/",
+            web::get().to(|htmx: Htmx| async move {
+                htmx.trigger_event("saved".to_string(), None, None);
+                htmx.vary_hx_request();
+                HttpResponse::Ok().body(format!("{} {:?}", htmx.is_htmx, htmx.target()))
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::get()
+            .uri("/")
+            .insert_header(("hx2-request", "true"))
+            .insert_header(("hx2-target", "list"));
+        let res = test::call_service(&app, req.to_request()).await;
+        assert_eq!(header(res.headers(), "hx2-trigger"), Some("saved"));
+        assert_eq!(header(res.headers(), "vary"), Some("hx2-request"));
+        assert!(res.headers().get("hx-trigger").is_none());
+        assert_eq!(test::read_body(res).await, r#"true Some("list")"#);
+
+        let res = test::call_service(&app, htmx_request().to_request()).await;
+        assert_eq!(test::read_body(res).await, "false None");
+    }
+
+    /// Collects the `name=value` fields of every span, both those given when the span is
+    /// created and those recorded on it later.
+    #[cfg(feature = "tracing")]
+    #[derive(Clone, Default)]
+    struct SpanFields(Arc<std::sync::Mutex<Vec<String>>>);
+
+    #[cfg(feature = "tracing")]
+    impl SpanFields {
+        fn install(&self) -> tracing::subscriber::DefaultGuard {
+            use tracing_subscriber::layer::SubscriberExt;
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(self.clone()))
+        }
+
+        fn fields(&self) -> Vec<String> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    impl tracing::field::Visit for SpanFields {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0.lock().unwrap().push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for SpanFields {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            _: &tracing::span::Id,
+            _: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            attrs.record(&mut self.clone());
+        }
+
+        fn on_record(
+            &self,
+            _: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            _: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            values.record(&mut self.clone());
+        }
+    }
+
+    #[actix_web::test]
+    #[cfg(feature = "tracing")]
+    #[tracing_test::traced_test]
+    async fn middleware_call_span_wraps_trigger_events() {
+        let app = test::init_service(App::new().wrap(HtmxMiddleware).route(
+            "/",
+            web::get().to(|htmx: Htmx| async move {
+                htmx.trigger_event("saved".to_string(), None, None);
+                htmx.trigger_event("swapped".to_string(), None, Some(TriggerType::AfterSwap));
+                HttpResponse::Ok().finish()
+            }),
+        ))
+        .await;
+        test::call_service(&app, htmx_request().to_request()).await;
+
+        assert!(logs_contain(
+            "htmx_middleware_call{is_htmx=true}: actix_htmx::htmx: htmx trigger queued name=saved trigger_type=hx-trigger"
+        ));
+        assert!(logs_contain("trigger_type=hx-trigger-after-swap"));
+    }
+
+    #[actix_web::test]
+    #[cfg(feature = "tracing")]
+    async fn middleware_call_span_records_trigger_counts() {
+        let spans = SpanFields::default();
+        let _guard = spans.install();
+        let app = test::init_service(App::new().wrap(HtmxMiddleware).route(
+            "/",
+            web::get().to(|htmx: Htmx| async move {
+                htmx.trigger_event("saved".to_string(), None, None);
+                htmx.trigger_event("toast".to_string(), None, None);
+                htmx.trigger_event("swapped".to_string(), None, Some(TriggerType::AfterSwap));
+                HttpResponse::Ok().finish()
+            }),
+        ))
+        .await;
+        test::call_service(&app, htmx_request().to_request()).await;
+
+        assert_eq!(
+            spans.fields(),
+            [
+                "is_htmx=true",
+                "trigger_count_standard=2",
+                "trigger_count_after_settle=0",
+                "trigger_count_after_swap=1",
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    #[cfg(feature = "tracing")]
+    async fn middleware_call_span_marks_plain_requests() {
+        let spans = SpanFields::default();
+        let _guard = spans.install();
+        let app = test::init_service(App::new().wrap(HtmxMiddleware).route("/", web::get().to(ok))).await;
+        test::call_service(&app, TestRequest::get().uri("/").to_request()).await;
+
+        assert_eq!(spans.fields()[..2], ["is_htmx=false", "trigger_count_standard=0"]);
+    }
+
+    /// Keeps the log records sent to targets under [`CAPTURE_TARGET`], with their key-values,
+    /// so tests can check what the middleware logged. Each test logs to its own target.
+    struct CapturingLogger {
+        records: std::sync::Mutex<Vec<(String, Level, String)>>,
+    }
+
+    const CAPTURE_TARGET: &str = "actix_htmx_captured";
+
+    impl CapturingLogger {
+        fn records(&self, target: &str) -> Vec<(Level, String)> {
+            self.records
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(record_target, _, _)| record_target == target)
+                .map(|(_, level, message)| (*level, message.clone()))
+                .collect()
+        }
+    }
+
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.target().starts_with(CAPTURE_TARGET)
+        }
+
+        fn log(&self, record: &log::Record) {
+            struct Fields(String);
+
+            impl<'kvs> log::kv::VisitSource<'kvs> for Fields {
+                fn visit_pair(
+                    &mut self,
+                    key: log::kv::Key<'kvs>,
+                    value: log::kv::Value<'kvs>,
+                ) -> Result<(), log::kv::Error> {
+                    let _ = write!(self.0, " {}={}", key, value);
+                    Ok(())
+                }
             }
 
-            Ok(ServiceResponse::new(req, res))
+            if self.enabled(record.metadata()) {
+                let mut fields = Fields(record.args().to_string());
+                let _ = record.key_values().visit(&mut fields);
+                let target = record.target().to_string();
+                self.records.lock().unwrap().push((target, record.level(), fields.0));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    fn captured_logs() -> &'static CapturingLogger {
+        static LOGGER: std::sync::OnceLock<&'static CapturingLogger> = std::sync::OnceLock::new();
+        LOGGER.get_or_init(|| {
+            let logger = Box::leak(Box::new(CapturingLogger {
+                records: std::sync::Mutex::new(Vec::new()),
+            }));
+            log::set_logger(logger).expect("no other logger is installed in tests");
+            log::set_max_level(log::LevelFilter::Debug);
+            logger
         })
     }
+
+    #[actix_web::test]
+    async fn debug_entries_describe_request_and_response() {
+        let logger = captured_logs();
+        let config = HtmxMiddlewareConfig {
+            log_target: "actix_htmx_captured::debug_entries",
+            ..HtmxMiddlewareConfig::default()
+        };
+        let app = test::init_service(App::new().wrap(HtmxMiddleware::with_config(config)).route(
+            "/",
+            web::get().to(|htmx: Htmx| async move {
+                htmx.trigger_event("saved".to_string(), None, None);
+                htmx.trigger_event("settled".to_string(), None, Some(TriggerType::AfterSettle));
+                htmx.redirect("/todos");
+                htmx.retarget("#list");
+                HttpResponse::Ok().finish()
+            }),
+        ))
+        .await;
+
+        let req = htmx_request().insert_header(("hx-target", "list"));
+        test::call_service(&app, req.to_request()).await;
+
+        assert_eq!(
+            logger.records("actix_htmx_captured::debug_entries"),
+            [
+                (
+                    Level::Debug,
+                    "htmx request is_htmx=true boosted=false history_restore_request=false \
+                     target=Some(\"list\") trigger=None current_url=None"
+                        .to_string()
+                ),
+                (
+                    Level::Debug,
+                    "htmx response standard_trigger_count=1 after_settle_trigger_count=1 \
+                     after_swap_trigger_count=0 response_header_names=hx-redirect,hx-retarget"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[derive(Default)]
+    struct TestMetrics {
+        htmx_requests: AtomicUsize,
+        non_htmx_requests: AtomicUsize,
+        triggers: AtomicUsize,
+        after_swap_triggers: AtomicUsize,
+        redirects: AtomicUsize,
+        refreshes: AtomicUsize,
+    }
+
+    impl HtmxMetrics for TestMetrics {
+        fn on_htmx_request(&self) {
+            self.htmx_requests.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn on_non_htmx_request(&self) {
+            self.non_htmx_requests.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn on_trigger(&self, _name: &str, trigger_type: TriggerType) {
+            self.triggers.fetch_add(1, Ordering::SeqCst);
+            if trigger_type == TriggerType::AfterSwap {
+                self.after_swap_triggers.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        fn on_redirect(&self) {
+            self.redirects.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn on_refresh(&self) {
+            self.refreshes.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[actix_web::test]
+    async fn metrics_count_requests_triggers_and_headers() {
+        let metrics = Arc::new(TestMetrics::default());
+        let config = HtmxMiddlewareConfig::default().with_metrics(metrics.clone());
+        let app = test::init_service(App::new().wrap(HtmxMiddleware::with_config(config)).route(
+            "/",
+            web::get().to(|htmx: Htmx| async move {
+                htmx.trigger_event("saved".to_string(), None, None);
+                htmx.trigger_event("swapped".to_string(), None, Some(TriggerType::AfterSwap));
+                htmx.redirect("/todos");
+                HttpResponse::Ok().finish()
+            }),
+        ))
+        .await;
+
+        test::call_service(&app, htmx_request().to_request()).await;
+        assert_eq!(metrics.htmx_requests.load(Ordering::SeqCst), 1);
+        assert_eq!(metrics.non_htmx_requests.load(Ordering::SeqCst), 0);
+        assert_eq!(metrics.triggers.load(Ordering::SeqCst), 2);
+        assert_eq!(metrics.after_swap_triggers.load(Ordering::SeqCst), 1);
+        assert_eq!(metrics.redirects.load(Ordering::SeqCst), 1);
+        assert_eq!(metrics.refreshes.load(Ordering::SeqCst), 0);
+
+        test::call_service(&app, TestRequest::get().uri("/").to_request()).await;
+        assert_eq!(metrics.htmx_requests.load(Ordering::SeqCst), 1);
+        assert_eq!(metrics.non_htmx_requests.load(Ordering::SeqCst), 1);
+        assert_eq!(metrics.triggers.load(Ordering::SeqCst), 4);
+        assert_eq!(metrics.redirects.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_web::test]
+    async fn log_level_and_target_apply_to_warnings() {
+        const TARGET: &str = "actix_htmx_captured::quiet";
+        let logger = captured_logs();
+        let config = HtmxMiddlewareConfig {
+            log_level: Level::Debug,
+            log_target: TARGET,
+            ..HtmxMiddlewareConfig::default()
+        };
+        let app = test::init_service(App::new().wrap(HtmxMiddleware::with_config(config)).route(
+            "/",
+            web::get().to(|htmx: Htmx| async move {
+                assert!(htmx.redirect_external("/local").is_err());
+                htmx.refresh();
+                htmx.push_url("/todos");
+                htmx.set_response_header("hx-retarget", "bad\nvalue");
+                HttpResponse::Ok().finish()
+            }),
+        ))
+        .await;
+        test::call_service(&app, htmx_request().to_request()).await;
+
+        let records = logger.records(TARGET);
+        let messages = records.iter().map(|(_, message)| message.as_str()).collect::<Vec<_>>();
+        assert!(messages.contains(&"Refusing to redirect to non-absolute url: /local"), "{messages:?}");
+        assert!(
+            messages.contains(&"Conflicting htmx response headers: HX-Push-Url is set alongside HX-Refresh"),
+            "{messages:?}"
+        );
+        assert!(messages.iter().any(|message| message.starts_with("Failed to parse hx-retarget")), "{messages:?}");
+        assert!(records.iter().all(|(level, _)| *level == Level::Debug), "{records:?}");
+    }
+
+    #[actix_web::test]
+    async fn on_header_error_receives_unwritable_headers() {
+        let errors = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = errors.clone();
+        let config = HtmxMiddlewareConfig {
+            on_header_error: Some(Box::new(move |name: &str, value: &str| {
+                seen.lock().unwrap().push((name.to_string(), value.to_string()));
+            })),
+            ..HtmxMiddlewareConfig::default()
+        };
+        let app = test::init_service(App::new().wrap(HtmxMiddleware::with_config(config)).route(
+            "/",
+            web::get().to(|htmx: Htmx| async move {
+                htmx.set_response_header("hx-retarget", "bad\nvalue");
+                htmx.set_response_header("hx bad name", "value");
+                htmx.refresh();
+                HttpResponse::Ok().finish()
+            }),
+        ))
+        .await;
+        let res = test::call_service(&app, htmx_request().to_request()).await;
+
+        assert_eq!(header(res.headers(), "hx-refresh"), Some("true"));
+        assert_eq!(
+            *errors.lock().unwrap(),
+            [
+                ("hx-retarget".to_string(), "bad\nvalue".to_string()),
+                ("hx bad name".to_string(), "value".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn trigger_json_escapes_names_and_messages() {
+        let mut triggers = IndexMap::new();
+        triggers.insert("say\"hi\"".to_string(), Some("back\\slash\tand \"quotes\"".to_string()));
+        triggers.insert("plain".to_string(), None);
+        triggers.insert("object".to_string(), Some("{\"id\": 1}".to_string()));
+
+        assert_eq!(
+            trigger_json(&triggers),
+            r#"{"say\"hi\"": "back\\slash\tand \"quotes\"","plain": null,"object": {"id": 1}}"#
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn trigger_json_parses_as_json() {
+        let mut triggers = IndexMap::new();
+        triggers.insert("odd\u{1}name".to_string(), Some("line\nbreak".to_string()));
+
+        let parsed: serde_json::Value = serde_json::from_str(&trigger_json(&triggers)).unwrap();
+        assert_eq!(parsed, serde_json::json!({ "odd\u{1}name": "line\nbreak" }));
+    }
 }
@@ -0,0 +1,286 @@
+use crate::{CssSelector, Htmx, HtmxArc, HxUrl, SwapType, TriggerType};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HtmxOperation {
+    Redirect(HxUrl),
+    Refresh,
+    PushUrl(HxUrl),
+    ReplaceUrl(HxUrl),
+    Reswap(SwapType),
+    Retarget(CssSelector),
+    Reselect(CssSelector),
+    Trigger {
+        name: String,
+        message: Option<String>,
+        trigger_type: TriggerType,
+    },
+    StopPolling,
+}
+
+mod sealed {
+    /// Implemented by [`crate::Htmx`] and [`crate::HtmxArc`], the types an [`super::HtmxResponse`]
+    /// or [`super::HtmxChain`] can be applied to.
+    pub trait ApplyOperation {
+        fn apply_operation(&self, operation: &super::HtmxOperation);
+    }
+}
+
+macro_rules! impl_apply_operation {
+    ($($htmx:ty),*) => {
+        $(
+            impl sealed::ApplyOperation for $htmx {
+                fn apply_operation(&self, operation: &HtmxOperation) {
+                    match operation {
+                        HtmxOperation::Redirect(path) => self.redirect(path.clone()),
+                        HtmxOperation::Refresh => self.refresh(),
+                        HtmxOperation::PushUrl(path) => self.push_url(path.clone()),
+                        HtmxOperation::ReplaceUrl(path) => self.replace_url(path.clone()),
+                        HtmxOperation::Reswap(swap_type) => self.reswap(swap_type.clone()),
+                        HtmxOperation::Retarget(selector) => self.retarget(selector.clone()),
+                        HtmxOperation::Reselect(selector) => self.reselect(selector.clone()),
+                        HtmxOperation::Trigger {
+                            name,
+                            message,
+                            trigger_type,
+                        } => self.trigger_event(name.clone(), message.clone(), Some(trigger_type.clone())),
+                        HtmxOperation::StopPolling => self.stop_polling(),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_apply_operation!(Htmx, HtmxArc);
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HtmxResponse {
+    operations: Vec<HtmxOperation>,
+}
+
+impl HtmxResponse {
+    pub fn new() -> HtmxResponse {
+        HtmxResponse::default()
+    }
+
+    pub fn redirect(mut self, path: impl Into<HxUrl>) -> Self {
+        self.operations.push(HtmxOperation::Redirect(path.into()));
+        self
+    }
+
+    pub fn refresh(mut self) -> Self {
+        self.operations.push(HtmxOperation::Refresh);
+        self
+    }
+
+    pub fn push_url(mut self, path: impl Into<HxUrl>) -> Self {
+        self.operations.push(HtmxOperation::PushUrl(path.into()));
+        self
+    }
+
+    pub fn replace_url(mut self, path: impl Into<HxUrl>) -> Self {
+        self.operations.push(HtmxOperation::ReplaceUrl(path.into()));
+        self
+    }
+
+    pub fn reswap(mut self, swap_type: SwapType) -> Self {
+        self.operations.push(HtmxOperation::Reswap(swap_type));
+        self
+    }
+
+    pub fn retarget(mut self, selector: impl Into<CssSelector>) -> Self {
+        self.operations.push(HtmxOperation::Retarget(selector.into()));
+        self
+    }
+
+    pub fn reselect(mut self, selector: impl Into<CssSelector>) -> Self {
+        self.operations.push(HtmxOperation::Reselect(selector.into()));
+        self
+    }
+
+    pub fn trigger_event(
+        mut self,
+        name: impl Into<String>,
+        message: Option<String>,
+        trigger_type: Option<TriggerType>,
+    ) -> Self {
+        self.operations.push(HtmxOperation::Trigger {
+            name: name.into(),
+            message,
+            trigger_type: trigger_type.unwrap_or(TriggerType::Standard),
+        });
+        self
+    }
+
+    pub fn stop_polling(mut self) -> Self {
+        self.operations.push(HtmxOperation::StopPolling);
+        self
+    }
+
+    pub fn operations(&self) -> &[HtmxOperation] {
+        &self.operations
+    }
+
+    /// Applies the operations, in order, to an [`Htmx`] or [`HtmxArc`].
+    pub fn apply(&self, htmx: &impl sealed::ApplyOperation) {
+        self.operations
+            .iter()
+            .for_each(|operation| htmx.apply_operation(operation));
+    }
+}
+
+pub struct HtmxChain<'a, H = Htmx> {
+    htmx: &'a H,
+    operations: Vec<HtmxOperation>,
+}
+
+impl<'a, H: sealed::ApplyOperation> HtmxChain<'a, H> {
+    pub(crate) fn new(htmx: &'a H) -> HtmxChain<'a, H> {
+        HtmxChain {
+            htmx,
+            operations: Vec::new(),
+        }
+    }
+
+    pub fn redirect(&mut self, path: impl Into<HxUrl>) -> &mut Self {
+        self.operations.push(HtmxOperation::Redirect(path.into()));
+        self
+    }
+
+    pub fn refresh(&mut self) -> &mut Self {
+        self.operations.push(HtmxOperation::Refresh);
+        self
+    }
+
+    pub fn push_url(&mut self, path: impl Into<HxUrl>) -> &mut Self {
+        self.operations.push(HtmxOperation::PushUrl(path.into()));
+        self
+    }
+
+    pub fn replace_url(&mut self, path: impl Into<HxUrl>) -> &mut Self {
+        self.operations.push(HtmxOperation::ReplaceUrl(path.into()));
+        self
+    }
+
+    pub fn reswap(&mut self, swap_type: SwapType) -> &mut Self {
+        self.operations.push(HtmxOperation::Reswap(swap_type));
+        self
+    }
+
+    pub fn retarget(&mut self, selector: impl Into<CssSelector>) -> &mut Self {
+        self.operations.push(HtmxOperation::Retarget(selector.into()));
+        self
+    }
+
+    pub fn reselect(&mut self, selector: impl Into<CssSelector>) -> &mut Self {
+        self.operations.push(HtmxOperation::Reselect(selector.into()));
+        self
+    }
+
+    pub fn trigger_event(
+        &mut self,
+        name: impl Into<String>,
+        message: Option<String>,
+        trigger_type: Option<TriggerType>,
+    ) -> &mut Self {
+        self.operations.push(HtmxOperation::Trigger {
+            name: name.into(),
+            message,
+            trigger_type: trigger_type.unwrap_or(TriggerType::Standard),
+        });
+        self
+    }
+
+    pub fn stop_polling(&mut self) -> &mut Self {
+        self.operations.push(HtmxOperation::StopPolling);
+        self
+    }
+
+    pub fn operations(&self) -> &[HtmxOperation] {
+        &self.operations
+    }
+
+    pub fn apply(&self) {
+        self.operations
+            .iter()
+            .for_each(|operation| self.htmx.apply_operation(operation));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{self, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+    use crate::HtmxMiddleware;
+
+    #[test]
+    fn htmx_response_records_operations_in_order() {
+        let response = HtmxResponse::new()
+            .retarget("#list")
+            .reswap(SwapType::BeforeEnd)
+            .trigger_event("created", None, None)
+            .stop_polling();
+
+        assert_eq!(
+            response.operations(),
+            [
+                HtmxOperation::Retarget(CssSelector::new("#list")),
+                HtmxOperation::Reswap(SwapType::BeforeEnd),
+                HtmxOperation::Trigger {
+                    name: "created".to_string(),
+                    message: None,
+                    trigger_type: TriggerType::Standard,
+                },
+                HtmxOperation::StopPolling,
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn htmx_response_apply_writes_headers() {
+        let app = test::init_service(App::new().wrap(HtmxMiddleware).route(
+            "/",
+            web::get().to(|htmx: Htmx| async move {
+                HtmxResponse::new()
+                    .push_url("/todos/1")
+                    .reselect("#item")
+                    .trigger_event("saved", None, Some(TriggerType::AfterSwap))
+                    .apply(&htmx);
+                HttpResponse::Ok().finish()
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::get().uri("/").insert_header(("hx-request", "true"));
+        let res = test::call_service(&app, req.to_request()).await;
+        assert_eq!(res.headers().get("hx-push-url").unwrap(), "/todos/1");
+        assert_eq!(res.headers().get("hx-reselect").unwrap(), "#item");
+        assert_eq!(res.headers().get("hx-trigger-after-swap").unwrap(), "saved");
+    }
+
+    #[test]
+    fn chain_applies_only_when_asked() {
+        let htmx = Htmx::mock();
+        let mut chain = htmx.chain();
+        chain
+            .redirect("/new")
+            .trigger_event("created", None, None)
+            .reswap(SwapType::OuterHtml);
+        assert_eq!(chain.operations().len(), 3);
+        assert!(!htmx.has_redirect());
+
+        chain.apply();
+        assert_eq!(htmx.get_response_header("hx-redirect").as_deref(), Some("/new"));
+        assert_eq!(htmx.get_response_header("hx-reswap").as_deref(), Some("outerHTML"));
+        assert!(htmx.has_trigger("created", TriggerType::Standard));
+    }
+
+    #[test]
+    fn dropped_chain_writes_nothing() {
+        let htmx = Htmx::mock();
+        htmx.chain().refresh().stop_polling();
+        assert!(!htmx.has_refresh());
+        assert!(!htmx.has_response_header("hx-stop-polling"));
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::{CssSelector, SwapModifier, SwapType};
+
+#[derive(Debug)]
+pub struct HxReswap {
+    swap_type: SwapType,
+    swap: Option<Duration>,
+    settle: Option<Duration>,
+    ignore_title: bool,
+    scroll: Option<CssSelector>,
+    show: Option<CssSelector>,
+    focus_scroll: Option<bool>,
+}
+
+impl HxReswap {
+    pub fn new(swap_type: SwapType) -> HxReswap {
+        HxReswap {
+            swap_type,
+            swap: None,
+            settle: None,
+            ignore_title: false,
+            scroll: None,
+            show: None,
+            focus_scroll: None,
+        }
+    }
+
+    pub fn swap_delay(mut self, delay: Duration) -> Self {
+        self.swap = Some(delay);
+        self
+    }
+
+    pub fn settle_delay(mut self, delay: Duration) -> Self {
+        self.settle = Some(delay);
+        self
+    }
+
+    pub fn ignore_title(mut self) -> Self {
+        self.ignore_title = true;
+        self
+    }
+
+    pub fn scroll_to(mut self, selector: impl Into<CssSelector>) -> Self {
+        self.scroll = Some(selector.into());
+        self
+    }
+
+    pub fn show(mut self, selector: impl Into<CssSelector>) -> Self {
+        self.show = Some(selector.into());
+        self
+    }
+
+    pub fn focus_scroll(mut self, focus_scroll: bool) -> Self {
+        self.focus_scroll = Some(focus_scroll);
+        self
+    }
+
+    pub fn build(&self) -> String {
+        self.to_string()
+    }
+
+    fn modifiers(&self) -> Vec<SwapModifier> {
+        let mut modifiers = Vec::new();
+        if let Some(delay) = self.swap {
+            modifiers.push(SwapModifier::Swap(delay));
+        }
+        if let Some(delay) = self.settle {
+            modifiers.push(SwapModifier::Settle(delay));
+        }
+        if self.ignore_title {
+            modifiers.push(SwapModifier::IgnoreTitle);
+        }
+        if let Some(selector) = &self.scroll {
+            modifiers.push(SwapModifier::Scroll(selector.clone()));
+        }
+        if let Some(selector) = &self.show {
+            modifiers.push(SwapModifier::Show(selector.clone()));
+        }
+        if let Some(focus_scroll) = self.focus_scroll {
+            modifiers.push(SwapModifier::FocusScroll(focus_scroll));
+        }
+        modifiers
+    }
+}
+
+impl fmt::Display for HxReswap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.swap_type)?;
+        self.modifiers()
+            .iter()
+            .try_for_each(|modifier| write!(f, " {}", modifier))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_modifier_in_isolation() {
+        let reswap = || HxReswap::new(SwapType::InnerHtml);
+        assert_eq!(reswap().build(), "innerHTML");
+        assert_eq!(reswap().swap_delay(Duration::from_millis(300)).build(), "innerHTML swap:300ms");
+        assert_eq!(reswap().settle_delay(Duration::from_millis(100)).build(), "innerHTML settle:100ms");
+        assert_eq!(reswap().ignore_title().build(), "innerHTML ignoreTitle:true");
+        assert_eq!(reswap().scroll_to("#result").build(), "innerHTML scroll:#result");
+        assert_eq!(reswap().show("#top").build(), "innerHTML show:#top");
+        assert_eq!(reswap().focus_scroll(true).build(), "innerHTML focus-scroll:true");
+    }
+
+    #[test]
+    fn modifiers_combine_in_a_fixed_order() {
+        let reswap = HxReswap::new(SwapType::OuterHtml)
+            .focus_scroll(false)
+            .scroll_to("#result")
+            .settle_delay(Duration::from_millis(100))
+            .swap_delay(Duration::from_millis(300));
+        assert_eq!(reswap.build(), "outerHTML swap:300ms settle:100ms scroll:#result focus-scroll:false");
+        assert_eq!(reswap.to_string(), reswap.build());
+    }
+
+    #[test]
+    fn apply_reswap_writes_built_value() {
+        let htmx = crate::Htmx::mock();
+        let reswap = htmx
+            .reswap_builder(SwapType::BeforeEnd)
+            .swap_delay(Duration::from_secs(1));
+        htmx.apply_reswap(reswap);
+        assert_eq!(htmx.get_response_header("hx-reswap").as_deref(), Some("beforeend swap:1000ms"));
+    }
+}
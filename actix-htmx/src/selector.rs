@@ -0,0 +1,32 @@
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CssSelector(String);
+
+impl CssSelector {
+    pub fn new(selector: impl Into<String>) -> CssSelector {
+        CssSelector(selector.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for CssSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<String> for CssSelector {
+    fn from(selector: String) -> Self {
+        CssSelector(selector)
+    }
+}
+
+impl From<&str> for CssSelector {
+    fn from(selector: &str) -> Self {
+        CssSelector(selector.to_string())
+    }
+}
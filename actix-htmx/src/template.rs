@@ -0,0 +1,60 @@
+use crate::{Htmx, HtmxArc, HtmxRequestInfo};
+
+/// Implemented by templates that render differently for htmx requests, e.g. a fragment
+/// instead of the full page.
+pub trait HtmxAwareTemplate {
+    fn set_htmx_context(&mut self, context: HtmxRequestInfo);
+}
+
+/// Passes the current request's [`HtmxRequestInfo`] to an [`HtmxAwareTemplate`].
+pub trait HtmxTemplateExt {
+    fn apply_to_template<T: HtmxAwareTemplate>(&self, template: &mut T);
+}
+
+impl HtmxTemplateExt for Htmx {
+    fn apply_to_template<T: HtmxAwareTemplate>(&self, template: &mut T) {
+        template.set_htmx_context(self.request_info());
+    }
+}
+
+impl HtmxTemplateExt for HtmxArc {
+    fn apply_to_template<T: HtmxAwareTemplate>(&self, template: &mut T) {
+        template.set_htmx_context(self.request_info());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[derive(Default)]
+    struct Page {
+        context: Option<HtmxRequestInfo>,
+    }
+
+    impl HtmxAwareTemplate for Page {
+        fn set_htmx_context(&mut self, context: HtmxRequestInfo) {
+            self.context = Some(context);
+        }
+    }
+
+    #[test]
+    fn apply_to_template_passes_request_info() {
+        let htmx = Htmx::mock_from_headers(&[("hx-request", "true"), ("hx-target", "list")]);
+        let mut page = Page::default();
+        htmx.apply_to_template(&mut page);
+        assert_eq!(page.context, Some(htmx.request_info()));
+        assert_eq!(page.context.unwrap().target.as_deref(), Some("list"));
+
+        let req = TestRequest::default()
+            .insert_header(("hx-request", "true"))
+            .insert_header(("hx-boosted", "true"))
+            .to_srv_request();
+        let htmx = HtmxArc::new(&req);
+        let mut page = Page::default();
+        htmx.apply_to_template(&mut page);
+        let context = page.context.unwrap();
+        assert!(context.is_htmx && context.boosted);
+    }
+}
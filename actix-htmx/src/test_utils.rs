@@ -0,0 +1,170 @@
+use actix_web::test::TestRequest;
+
+use crate::headers::RequestHeaders;
+
+pub struct HtmxTestBuilder {
+    request: TestRequest,
+}
+
+impl HtmxTestBuilder {
+    pub fn new(request: TestRequest) -> HtmxTestBuilder {
+        HtmxTestBuilder { request }
+    }
+
+    pub fn new_get(path: &str) -> HtmxTestBuilder {
+        HtmxTestBuilder::new(TestRequest::get().uri(path))
+    }
+
+    pub fn new_post(path: &str) -> HtmxTestBuilder {
+        HtmxTestBuilder::new(TestRequest::post().uri(path))
+    }
+
+    pub fn new_put(path: &str) -> HtmxTestBuilder {
+        HtmxTestBuilder::new(TestRequest::put().uri(path))
+    }
+
+    pub fn new_delete(path: &str) -> HtmxTestBuilder {
+        HtmxTestBuilder::new(TestRequest::delete().uri(path))
+    }
+
+    pub fn htmx(self, is_htmx: bool) -> Self {
+        HtmxTestBuilder::new(self.request.htmx(is_htmx))
+    }
+
+    pub fn boosted(self, boosted: bool) -> Self {
+        HtmxTestBuilder::new(self.request.boosted(boosted))
+    }
+
+    pub fn history_restore_request(self, history_restore_request: bool) -> Self {
+        HtmxTestBuilder::new(self.request.history_restore_request(history_restore_request))
+    }
+
+    pub fn current_url(self, url: impl Into<String>) -> Self {
+        HtmxTestBuilder::new(self.request.current_url(url))
+    }
+
+    pub fn prompt(self, prompt: impl Into<String>) -> Self {
+        HtmxTestBuilder::new(self.request.prompt(prompt))
+    }
+
+    pub fn target(self, target: impl Into<String>) -> Self {
+        HtmxTestBuilder::new(self.request.target(target))
+    }
+
+    pub fn trigger(self, trigger: impl Into<String>) -> Self {
+        HtmxTestBuilder::new(self.request.trigger(trigger))
+    }
+
+    pub fn trigger_name(self, trigger_name: impl Into<String>) -> Self {
+        HtmxTestBuilder::new(self.request.trigger_name(trigger_name))
+    }
+
+    pub fn build(self) -> TestRequest {
+        self.request
+    }
+}
+
+pub trait TestRequestHtmxExt {
+    fn htmx(self, is_htmx: bool) -> Self;
+    fn boosted(self, boosted: bool) -> Self;
+    fn history_restore_request(self, history_restore_request: bool) -> Self;
+    fn current_url(self, url: impl Into<String>) -> Self;
+    fn prompt(self, prompt: impl Into<String>) -> Self;
+    fn target(self, target: impl Into<String>) -> Self;
+    fn trigger(self, trigger: impl Into<String>) -> Self;
+    fn trigger_name(self, trigger_name: impl Into<String>) -> Self;
+}
+
+impl TestRequestHtmxExt for TestRequest {
+    fn htmx(self, is_htmx: bool) -> Self {
+        self.insert_header((RequestHeaders::HX_REQUEST, is_htmx.to_string()))
+    }
+
+    fn boosted(self, boosted: bool) -> Self {
+        self.insert_header((RequestHeaders::HX_BOOSTED, boosted.to_string()))
+    }
+
+    fn history_restore_request(self, history_restore_request: bool) -> Self {
+        self.insert_header((
+            RequestHeaders::HX_HISTORY_RESTORE_REQUEST,
+            history_restore_request.to_string(),
+        ))
+    }
+
+    fn current_url(self, url: impl Into<String>) -> Self {
+        self.insert_header((RequestHeaders::HX_CURRENT_URL, url.into()))
+    }
+
+    fn prompt(self, prompt: impl Into<String>) -> Self {
+        self.insert_header((RequestHeaders::HX_PROMPT, prompt.into()))
+    }
+
+    fn target(self, target: impl Into<String>) -> Self {
+        self.insert_header((RequestHeaders::HX_TARGET, target.into()))
+    }
+
+    fn trigger(self, trigger: impl Into<String>) -> Self {
+        self.insert_header((RequestHeaders::HX_TRIGGER, trigger.into()))
+    }
+
+    fn trigger_name(self, trigger_name: impl Into<String>) -> Self {
+        self.insert_header((RequestHeaders::HX_TRIGGER_NAME, trigger_name.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Htmx, HtmxRequestInfo};
+
+    #[test]
+    fn builder_sets_every_htmx_header() {
+        let req = HtmxTestBuilder::new_post("/todos")
+            .htmx(true)
+            .boosted(true)
+            .history_restore_request(false)
+            .current_url("http://example.com/todos")
+            .prompt("yes")
+            .target("#list")
+            .trigger("save-button")
+            .trigger_name("save")
+            .build()
+            .to_http_request();
+
+        let header = |name: &str| req.headers().get(name).and_then(|value| value.to_str().ok());
+        assert_eq!(req.method(), "POST");
+        assert_eq!(req.path(), "/todos");
+        assert_eq!(header("hx-request"), Some("true"));
+        assert_eq!(header("hx-boosted"), Some("true"));
+        assert_eq!(header("hx-history-restore-request"), Some("false"));
+        assert_eq!(header("hx-current-url"), Some("http://example.com/todos"));
+        assert_eq!(header("hx-prompt"), Some("yes"));
+        assert_eq!(header("hx-target"), Some("#list"));
+        assert_eq!(header("hx-trigger"), Some("save-button"));
+        assert_eq!(header("hx-trigger-name"), Some("save"));
+    }
+
+    #[test]
+    fn extension_methods_round_trip_through_htmx() {
+        let req = TestRequest::get()
+            .uri("/")
+            .htmx(true)
+            .history_restore_request(true)
+            .target("#list")
+            .to_srv_request();
+
+        assert_eq!(
+            Htmx::new(&req).request_info(),
+            HtmxRequestInfo {
+                is_htmx: true,
+                boosted: false,
+                history_restore_request: true,
+                current_url: None,
+                prompt: None,
+                target: Some("#list".to_string()),
+                trigger: None,
+                trigger_name: None,
+            }
+        );
+    }
+}
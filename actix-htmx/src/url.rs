@@ -0,0 +1,115 @@
+use std::error::Error;
+use std::fmt;
+
+#[cfg(feature = "url-parsing")]
+use url::{ParseError, Url};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct HxUrl(String);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HxUrlError {
+    Empty,
+    Invalid(String),
+}
+
+impl HxUrl {
+    pub fn new(url: impl Into<String>) -> HxUrl {
+        HxUrl(url.into())
+    }
+
+    /// Rejects empty values and values containing whitespace or control characters. With the
+    /// `url-parsing` feature, absolute urls are also checked to parse.
+    pub fn try_new(url: impl Into<String>) -> Result<HxUrl, HxUrlError> {
+        let url = url.into();
+        if url.is_empty() {
+            return Err(HxUrlError::Empty);
+        }
+        let plain = !url.chars().any(|c| c.is_whitespace() || c.is_control());
+        #[cfg(feature = "url-parsing")]
+        let valid = match Url::parse(&url) {
+            Ok(_) => true,
+            Err(ParseError::RelativeUrlWithoutBase) => plain,
+            Err(_) => false,
+        };
+        #[cfg(not(feature = "url-parsing"))]
+        let valid = plain;
+
+        if valid {
+            Ok(HxUrl(url))
+        } else {
+            Err(HxUrlError::Invalid(url))
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for HxUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<String> for HxUrl {
+    fn from(url: String) -> Self {
+        HxUrl(url)
+    }
+}
+
+impl From<&str> for HxUrl {
+    fn from(url: &str) -> Self {
+        HxUrl(url.to_string())
+    }
+}
+
+impl fmt::Display for HxUrlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HxUrlError::Empty => write!(f, "url is empty"),
+            HxUrlError::Invalid(url) => write!(f, "invalid url: {}", url),
+        }
+    }
+}
+
+impl Error for HxUrlError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_new_accepts_paths_and_urls() {
+        assert_eq!(HxUrl::try_new("/todos?page=2").unwrap().as_str(), "/todos?page=2");
+        assert_eq!(
+            HxUrl::try_new("https://example.com/todos").unwrap().as_str(),
+            "https://example.com/todos"
+        );
+    }
+
+    #[test]
+    fn try_new_rejects_empty_and_whitespace() {
+        assert_eq!(HxUrl::try_new(""), Err(HxUrlError::Empty));
+        assert_eq!(
+            HxUrl::try_new("/todo list"),
+            Err(HxUrlError::Invalid("/todo list".to_string()))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "url-parsing")]
+    fn try_new_checks_absolute_urls() {
+        assert_eq!(
+            HxUrl::try_new("http://[::1"),
+            Err(HxUrlError::Invalid("http://[::1".to_string()))
+        );
+    }
+
+    #[test]
+    fn unchecked_constructors_keep_value() {
+        assert_eq!(HxUrl::new("not checked").to_string(), "not checked");
+        assert_eq!(HxUrl::from("/a").as_str(), "/a");
+    }
+}
@@ -19,7 +19,7 @@ pub async fn update_todo(
 ) -> HttpResponse {
     let ToDoStatus { completed } = form.0;
 
-    let status = if let None = completed {
+    let status = if completed.is_none() {
         Status::Pending
     } else {
         Status::Done